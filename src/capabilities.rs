@@ -12,6 +12,8 @@ pub trait ClientCapabilitiesExt {
     fn has_pull_configuration_support(&self) -> bool;
 
     fn has_push_configuration_support(&self) -> bool;
+
+    fn has_file_watching_support(&self) -> bool;
 }
 
 impl ClientCapabilitiesExt for ClientCapabilities {
@@ -55,6 +57,14 @@ impl ClientCapabilitiesExt for ClientCapabilities {
             .and_then(|cap| cap.dynamic_registration)
             == Some(true)
     }
+
+    fn has_file_watching_support(&self) -> bool {
+        self.workspace
+            .as_ref()
+            .and_then(|cap| cap.did_change_watched_files.as_ref())
+            .and_then(|cap| cap.dynamic_registration)
+            == Some(true)
+    }
 }
 
 #[cfg(test)]
@@ -145,4 +155,19 @@ mod tests {
         let capabilities = ClientCapabilities::default();
         assert!(!capabilities.has_hover_markdown_support());
     }
+
+    #[test]
+    fn test_has_file_watching_support_true() {
+        let capabilities: ClientCapabilities = serde_json::from_value(serde_json::json!({
+            "workspace": { "didChangeWatchedFiles": { "dynamicRegistration": true } }
+        }))
+        .unwrap();
+        assert!(capabilities.has_file_watching_support());
+    }
+
+    #[test]
+    fn test_has_file_watching_support_false() {
+        let capabilities = ClientCapabilities::default();
+        assert!(!capabilities.has_file_watching_support());
+    }
 }
@@ -0,0 +1,291 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    create_workspace_headless, diagnostics::DiagnosticsManager, DocumentLanguage, Options,
+    ServerContext, Uri, Workspace,
+};
+
+/// The output format of the `check` subcommand.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CheckFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+impl FromStr for CheckFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            _ => bail!("Unknown output format: {}", value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiagnostics {
+    pub uri: Uri,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    pub files: Vec<FileDiagnostics>,
+}
+
+/// Loads all documents inside of the given directory and runs the static analyses
+/// (LaTeX, BibTeX and build logs) on them. `chktex` is only run if `chktex` is set.
+pub fn check_project(root: &Path, options: Options, chktex: bool) -> Result<CheckReport> {
+    let context = ServerContext::with_folder(root.canonicalize()?, options.clone());
+    let workspace = create_workspace_headless(Arc::new(context))?;
+
+    let documents = workspace.documents();
+    let mut manager = DiagnosticsManager::default();
+    for document in &documents {
+        manager.update_static(&workspace, Arc::clone(&document.uri));
+        if chktex && document.language() == DocumentLanguage::Latex {
+            manager.update_chktex(&workspace, Arc::clone(&document.uri), &options);
+        }
+    }
+
+    let mut files: Vec<_> = documents
+        .into_iter()
+        .map(|document| FileDiagnostics {
+            uri: document.uri.as_ref().clone(),
+            diagnostics: manager.publish(Arc::clone(&document.uri)),
+        })
+        .filter(|file| !file.diagnostics.is_empty())
+        .collect();
+
+    files.sort_by(|a, b| a.uri.as_str().cmp(b.uri.as_str()));
+    for file in &mut files {
+        file.diagnostics.sort_by_key(|diagnostic| {
+            (
+                diagnostic.range.start.line,
+                diagnostic.range.start.character,
+            )
+        });
+    }
+
+    Ok(CheckReport { files })
+}
+
+impl CheckReport {
+    pub fn count(&self, severity: DiagnosticSeverity) -> usize {
+        self.files
+            .iter()
+            .flat_map(|file| &file.diagnostics)
+            .filter(|diagnostic| {
+                diagnostic.severity.unwrap_or(DiagnosticSeverity::Error) == severity
+            })
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(DiagnosticSeverity::Error) > 0
+    }
+
+    pub fn render(&self, format: CheckFormat, root: &Path) -> String {
+        match format {
+            CheckFormat::Text => self.to_text(root),
+            CheckFormat::Json => serde_json::to_string_pretty(self).unwrap(),
+            CheckFormat::Sarif => serde_json::to_string_pretty(&self.to_sarif()).unwrap(),
+        }
+    }
+
+    fn to_text(&self, root: &Path) -> String {
+        let mut text = String::new();
+        for file in &self.files {
+            let path = display_path(&file.uri, root);
+            for diagnostic in &file.diagnostics {
+                let position = diagnostic.range.start;
+                write!(
+                    text,
+                    "{}:{}:{}: {}: {}",
+                    path,
+                    position.line + 1,
+                    position.character + 1,
+                    severity_name(diagnostic.severity),
+                    diagnostic.message
+                )
+                .unwrap();
+
+                if let Some(source) = &diagnostic.source {
+                    match &diagnostic.code {
+                        Some(code) => write!(text, " [{} {}]", source, code_name(code)).unwrap(),
+                        None => write!(text, " [{}]", source).unwrap(),
+                    }
+                }
+
+                text.push('\n');
+            }
+        }
+
+        writeln!(
+            text,
+            "{} error(s), {} warning(s)",
+            self.count(DiagnosticSeverity::Error),
+            self.count(DiagnosticSeverity::Warning)
+        )
+        .unwrap();
+        text
+    }
+
+    fn to_sarif(&self) -> serde_json::Value {
+        let results: Vec<_> = self
+            .files
+            .iter()
+            .flat_map(|file| {
+                file.diagnostics
+                    .iter()
+                    .map(move |diagnostic| sarif_result(&file.uri, diagnostic))
+            })
+            .collect();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "texlab",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/latex-lsp/texlab",
+                    }
+                },
+                "results": results,
+            }]
+        })
+    }
+}
+
+fn sarif_result(uri: &Uri, diagnostic: &Diagnostic) -> serde_json::Value {
+    let level = match diagnostic.severity {
+        Some(DiagnosticSeverity::Warning) => "warning",
+        Some(DiagnosticSeverity::Information) | Some(DiagnosticSeverity::Hint) => "note",
+        _ => "error",
+    };
+
+    let rule_id = match (&diagnostic.source, &diagnostic.code) {
+        (Some(source), Some(code)) => format!("{}/{}", source, code_name(code)),
+        (Some(source), None) => source.clone(),
+        (None, Some(code)) => code_name(code),
+        (None, None) => "texlab".to_string(),
+    };
+
+    let range = diagnostic.range;
+    json!({
+        "ruleId": rule_id,
+        "level": level,
+        "message": { "text": diagnostic.message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri.as_str() },
+                "region": {
+                    "startLine": range.start.line + 1,
+                    "startColumn": range.start.character + 1,
+                    "endLine": range.end.line + 1,
+                    "endColumn": range.end.character + 1,
+                }
+            }
+        }]
+    })
+}
+
+fn display_path(uri: &Uri, root: &Path) -> String {
+    match uri.to_file_path() {
+        Ok(path) => path
+            .strip_prefix(root)
+            .map(PathBuf::from)
+            .unwrap_or(path)
+            .display()
+            .to_string(),
+        Err(()) => uri.to_string(),
+    }
+}
+
+fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::Warning) => "warning",
+        Some(DiagnosticSeverity::Information) => "info",
+        Some(DiagnosticSeverity::Hint) => "hint",
+        _ => "error",
+    }
+}
+
+fn code_name(code: &NumberOrString) -> String {
+    match code {
+        NumberOrString::Number(number) => number.to_string(),
+        NumberOrString::String(string) => string.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn create_project(root: &Path) {
+        fs::write(
+            root.join("main.tex"),
+            "\\documentclass{article}\n\\begin{document}\n\\begin{foo}\n\\end{document}\n",
+        )
+        .unwrap();
+        fs::write(root.join("refs.bib"), "@article{foo, title = {Bar}}\n").unwrap();
+    }
+
+    #[test]
+    fn test_check_project() {
+        let directory = tempdir().unwrap();
+        create_project(directory.path());
+
+        let report = check_project(directory.path(), Options::default(), false).unwrap();
+        assert!(report.has_errors());
+        assert_eq!(report.files.len(), 1);
+        assert!(report.files[0].uri.as_str().ends_with("/main.tex"));
+    }
+
+    #[test]
+    fn test_check_project_clean() {
+        let directory = tempdir().unwrap();
+        fs::write(directory.path().join("main.tex"), "\\section{Foo}\n").unwrap();
+
+        let report = check_project(directory.path(), Options::default(), false).unwrap();
+        assert!(!report.has_errors());
+        assert_eq!(
+            report.render(CheckFormat::Text, directory.path()),
+            "0 error(s), 0 warning(s)\n"
+        );
+    }
+
+    #[test]
+    fn test_render_sarif() {
+        let directory = tempdir().unwrap();
+        create_project(directory.path());
+
+        let report = check_project(directory.path(), Options::default(), false).unwrap();
+        let sarif: serde_json::Value =
+            serde_json::from_str(&report.render(CheckFormat::Sarif, directory.path())).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(sarif["runs"][0]["results"][0]["level"], "error");
+    }
+}
@@ -32,6 +32,22 @@ where
     Ok(result)
 }
 
+/// Sends a request to the client without waiting for the response.
+/// The response is discarded once it arrives.
+pub fn send_request_without_waiting<R>(
+    req_queue: &Mutex<ReqQueue>,
+    lsp_sender: &Sender<Message>,
+    params: R::Params,
+) -> Result<()>
+where
+    R: lsp_types::request::Request,
+    R::Params: Serialize,
+    R::Result: DeserializeOwned,
+{
+    register_outgoing_request::<R>(req_queue, lsp_sender, params)?;
+    Ok(())
+}
+
 fn register_outgoing_request<R>(
     req_queue: &Mutex<ReqQueue>,
     lsp_sender: &Sender<Message>,
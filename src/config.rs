@@ -1,5 +1,10 @@
-use std::sync::{Mutex, RwLock};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Mutex},
+};
 
+use anyhow::Result;
 use crossbeam_channel::Sender;
 use log::{error, warn};
 use lsp_server::Message;
@@ -8,8 +13,11 @@ use lsp_types::{
     request::{RegisterCapability, WorkspaceConfiguration},
     ClientCapabilities, ConfigurationItem, ConfigurationParams, Registration, RegistrationParams,
 };
+use serde::Deserialize;
 
-use crate::{client::send_request, req_queue::ReqQueue, ClientCapabilitiesExt, Options};
+use crate::{
+    client::send_request, req_queue::ReqQueue, ClientCapabilitiesExt, Options, ServerContext, Uri,
+};
 
 pub fn register_config_capability(
     req_queue: &Mutex<ReqQueue>,
@@ -41,50 +49,201 @@ pub fn register_config_capability(
     }
 }
 
-pub fn pull_config(
-    req_queue: &Mutex<ReqQueue>,
-    sender: &Sender<Message>,
-    options: &RwLock<Options>,
-    client_capabilities: &ClientCapabilities,
-) {
-    if !client_capabilities.has_pull_configuration_support() {
+pub fn pull_config(req_queue: &Mutex<ReqQueue>, sender: &Sender<Message>, context: &ServerContext) {
+    if !context
+        .client_capabilities
+        .lock()
+        .unwrap()
+        .has_pull_configuration_support()
+    {
         return;
     }
 
-    let params = ConfigurationParams {
-        items: vec![ConfigurationItem {
+    if !context.config_outdated.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    let folders = { context.workspace_folders.read().unwrap().clone() };
+    let items = std::iter::once(None)
+        .chain(
+            folders
+                .iter()
+                .map(|folder| Some(folder.as_ref().clone().into())),
+        )
+        .map(|scope_uri| ConfigurationItem {
             section: Some("texlab".to_string()),
-            scope_uri: None,
-        }],
-    };
+            scope_uri,
+        })
+        .collect();
 
+    let params = ConfigurationParams { items };
     match send_request::<WorkspaceConfiguration>(req_queue, sender, params) {
-        Ok(mut json) => {
-            let value = json.pop().expect("invalid configuration request");
-            let new_options = match serde_json::from_value(value) {
-                Ok(new_options) => new_options,
-                Err(why) => {
-                    warn!("Invalid configuration section \"texlab\": {}", why);
-                    Options::default()
-                }
-            };
-
-            let mut options = options.write().unwrap();
-            *options = new_options;
+        Ok(json) => {
+            let mut values = json.into_iter();
+            let value = values.next().expect("invalid configuration request");
+            *context.options.write().unwrap() = parse_options(value);
+
+            let folder_options = folders
+                .into_iter()
+                .zip(values)
+                .map(|(folder, value)| {
+                    let mut options = parse_options(value);
+                    resolve_root_directory(&folder, &mut options);
+                    (folder, options)
+                })
+                .collect();
+
+            *context.folder_options.write().unwrap() = folder_options;
         }
         Err(why) => {
             error!("Retrieving configuration failed: {}", why);
+            context.config_outdated.store(true, Ordering::SeqCst);
         }
     };
 }
 
-pub fn push_config(options: &RwLock<Options>, config: serde_json::Value) {
+fn parse_options(value: serde_json::Value) -> Options {
+    match serde_json::from_value(value) {
+        Ok(options) => options,
+        Err(why) => {
+            warn!("Invalid configuration section \"texlab\": {}", why);
+            Options::default()
+        }
+    }
+}
+
+/// Interprets a relative root directory of a workspace folder relative to the folder itself.
+fn resolve_root_directory(folder: &Uri, options: &mut Options) {
+    if let (Some(root_dir), Ok(folder_path)) = (&options.root_directory, folder.to_file_path()) {
+        options.root_directory = Some(folder_path.join(root_dir));
+    }
+}
+
+/// The name of the file that configures the command line interface of a project.
+pub const PROJECT_CONFIG_FILE: &str = ".texlab.json";
+
+/// Loads the options from the nearest `.texlab.json` file in the given directory or its ancestors.
+/// The file contains the same settings as the `texlab` configuration section of the editor.
+pub fn load_project_options(directory: &Path) -> Result<Options> {
+    match find_project_config(directory) {
+        Some(path) => {
+            let mut options: Options = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            if let (Some(root_dir), Some(directory)) = (&options.root_directory, path.parent()) {
+                options.root_directory = Some(directory.join(root_dir));
+            }
+
+            Ok(options)
+        }
+        None => Ok(Options::default()),
+    }
+}
+
+/// The indentation settings of a `.texlab.json` file.
+/// Editors send them with every formatting request,
+/// so they are only used by the command line interface.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectIndentOptions {
+    pub tab_size: Option<u32>,
+    pub insert_spaces: Option<bool>,
+}
+
+/// Loads the indentation settings from the nearest `.texlab.json` file
+/// in the given directory or its ancestors.
+pub fn load_project_indent_options(directory: &Path) -> Result<ProjectIndentOptions> {
+    match find_project_config(directory) {
+        Some(path) => Ok(serde_json::from_str(&fs::read_to_string(&path)?)?),
+        None => Ok(ProjectIndentOptions::default()),
+    }
+}
+
+fn find_project_config(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .map(|directory| directory.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+pub fn push_config(context: &ServerContext, config: serde_json::Value) {
     match serde_json::from_value(config) {
         Ok(new_options) => {
-            *options.write().unwrap() = new_options;
+            *context.options.write().unwrap() = new_options;
+            // The pushed settings are not scoped to a workspace folder,
+            // so they replace the settings of the folders as well.
+            context.folder_options.write().unwrap().clear();
         }
         Err(why) => {
             error!("Invalid configuration: {}", why);
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_load_project_options() {
+        let directory = tempdir().unwrap();
+        fs::write(
+            directory.path().join(PROJECT_CONFIG_FILE),
+            r#"{ "formatterLineLength": 120, "rootDirectory": "src" }"#,
+        )
+        .unwrap();
+        let child = directory.path().join("chapters");
+        fs::create_dir(&child).unwrap();
+
+        let options = load_project_options(&child).unwrap();
+        assert_eq!(options.formatter_line_length, Some(120));
+        assert_eq!(options.root_directory, Some(directory.path().join("src")));
+    }
+
+    #[test]
+    fn test_load_project_indent_options() {
+        let directory = tempdir().unwrap();
+        fs::write(
+            directory.path().join(PROJECT_CONFIG_FILE),
+            r#"{ "tabSize": 4, "insertSpaces": false, "formatterLineLength": 120 }"#,
+        )
+        .unwrap();
+
+        let options = load_project_indent_options(directory.path()).unwrap();
+        assert_eq!(
+            options,
+            ProjectIndentOptions {
+                tab_size: Some(4),
+                insert_spaces: Some(false),
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_project_options_missing() {
+        let directory = tempdir().unwrap();
+        let options = load_project_options(directory.path()).unwrap();
+        assert_eq!(options, Options::default());
+    }
+
+    #[test]
+    fn test_push_config_replaces_folder_options() {
+        let context = ServerContext::new(std::env::temp_dir());
+        let folder = Arc::new(Uri::parse("file:///papers/foo").unwrap());
+        let options = Options {
+            formatter_line_length: Some(1),
+            ..Options::default()
+        };
+        context
+            .folder_options
+            .write()
+            .unwrap()
+            .insert(Arc::clone(&folder), options);
+
+        push_config(&context, serde_json::json!({ "formatterLineLength": 2 }));
+        let options = context.options_for(&Uri::parse("file:///papers/foo/main.tex").unwrap());
+        assert_eq!(options.formatter_line_length, Some(2));
+    }
+}
@@ -1,13 +1,14 @@
 use std::{
     path::PathBuf,
-    sync::{Mutex, RwLock},
+    sync::{atomic::AtomicBool, Arc, Mutex, RwLock},
 };
 
 use lsp_types::{ClientCapabilities, ClientInfo};
+use rustc_hash::FxHashMap;
 
 use crate::{
     distro::{DistributionKind, Resolver},
-    Options,
+    Options, PositionEncoding, ServerStatistics, Uri,
 };
 
 #[derive(Debug)]
@@ -18,6 +19,16 @@ pub struct ServerContext {
     pub client_capabilities: Mutex<ClientCapabilities>,
     pub client_info: Mutex<Option<ClientInfo>>,
     pub options: RwLock<Options>,
+    pub workspace_folders: RwLock<Vec<Arc<Uri>>>,
+    pub folder_options: RwLock<FxHashMap<Arc<Uri>, Options>>,
+    /// Set if the configuration needs to be pulled from the client (`workspace/configuration`)
+    /// before the next request. Cleared once the configuration has been retrieved.
+    pub config_outdated: AtomicBool,
+    /// Set if the client notifies the server about changed files (`workspace/didChangeWatchedFiles`).
+    pub client_watches_files: AtomicBool,
+    /// The position encoding negotiated with the client (`general.positionEncodings`).
+    pub position_encoding: Mutex<PositionEncoding>,
+    pub statistics: ServerStatistics,
 }
 
 impl ServerContext {
@@ -29,6 +40,100 @@ impl ServerContext {
             client_capabilities: Mutex::default(),
             client_info: Mutex::default(),
             options: RwLock::default(),
+            workspace_folders: RwLock::default(),
+            folder_options: RwLock::default(),
+            config_outdated: AtomicBool::new(true),
+            client_watches_files: AtomicBool::default(),
+            position_encoding: Mutex::default(),
+            statistics: ServerStatistics::default(),
         }
     }
+
+    /// Creates a context with a single workspace folder (used by the command line interface).
+    pub fn with_folder(root: PathBuf, options: Options) -> Self {
+        let context = Self::new(root.clone());
+        *context.options.write().unwrap() = options;
+        if let Ok(folder) = Uri::from_directory_path(&root) {
+            context
+                .workspace_folders
+                .write()
+                .unwrap()
+                .push(Arc::new(folder));
+        }
+        context
+    }
+
+    /// Returns the options of the innermost workspace folder containing the given document
+    /// (or the folder itself). Documents outside of any workspace folder use the global options.
+    pub fn options_for(&self, uri: &Uri) -> Options {
+        self.folder_options
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(folder, _)| is_inside_folder(folder, uri))
+            .max_by_key(|(folder, _)| folder.as_str().len())
+            .map(|(_, options)| options.clone())
+            .unwrap_or_else(|| self.options.read().unwrap().clone())
+    }
+}
+
+pub(crate) fn is_inside_folder(folder: &Uri, uri: &Uri) -> bool {
+    let folder = folder.as_str().trim_end_matches('/');
+    uri.as_str()
+        .strip_prefix(folder)
+        .map_or(false, |path| path.is_empty() || path.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_context() -> ServerContext {
+        let context = ServerContext::new(std::env::temp_dir());
+        context.options.write().unwrap().formatter_line_length = Some(1);
+
+        let mut folder_options = context.folder_options.write().unwrap();
+        for (folder, line_length) in &[("file:///papers/foo", 2), ("file:///papers/foo/bar/", 3)] {
+            let options = Options {
+                formatter_line_length: Some(*line_length),
+                ..Options::default()
+            };
+            folder_options.insert(Arc::new(Uri::parse(folder).unwrap()), options);
+        }
+        drop(folder_options);
+        context
+    }
+
+    fn line_length(context: &ServerContext, uri: &str) -> Option<i32> {
+        context
+            .options_for(&Uri::parse(uri).unwrap())
+            .formatter_line_length
+    }
+
+    #[test]
+    fn test_options_for_outside_folder() {
+        let context = create_context();
+        assert_eq!(
+            line_length(&context, "file:///papers/foobar/main.tex"),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_options_for_folder() {
+        let context = create_context();
+        assert_eq!(
+            line_length(&context, "file:///papers/foo/main.tex"),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_options_for_nested_folder() {
+        let context = create_context();
+        assert_eq!(
+            line_length(&context, "file:///papers/foo/bar/main.tex"),
+            Some(3)
+        );
+    }
 }
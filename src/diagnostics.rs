@@ -3,16 +3,23 @@ mod build_log;
 mod chktex;
 mod debouncer;
 mod latex;
+mod report;
 
-use std::sync::Arc;
+use std::{
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
 
 use lsp_types::Diagnostic;
 use multimap::MultiMap;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHasher};
 
 use crate::{Options, Uri, Workspace};
 
-pub use self::debouncer::{DiagnosticsDebouncer, DiagnosticsMessage};
+pub use self::{
+    debouncer::{DiagnosticsDebouncer, DiagnosticsMessage},
+    report::*,
+};
 
 use self::{
     bibtex::analyze_bibtex_static, build_log::analyze_build_log_static,
@@ -23,14 +30,13 @@ use self::{
 pub struct DiagnosticsManager {
     static_diagnostics: FxHashMap<Arc<Uri>, MultiMap<Arc<Uri>, Diagnostic>>,
     chktex_diagnostics: MultiMap<Arc<Uri>, Diagnostic>,
+    pub pull_support: bool,
+    pub refresh_support: bool,
 }
 
 impl DiagnosticsManager {
     pub fn update_static(&mut self, workspace: &dyn Workspace, uri: Arc<Uri>) {
-        let mut diagnostics_by_uri = MultiMap::new();
-        analyze_build_log_static(workspace, &mut diagnostics_by_uri, &uri);
-        analyze_bibtex_static(workspace, &mut diagnostics_by_uri, &uri);
-        analyze_latex_static(workspace, &mut diagnostics_by_uri, &uri);
+        let diagnostics_by_uri = analyze_static(workspace, &uri);
         self.static_diagnostics.insert(uri, diagnostics_by_uri);
     }
 
@@ -38,6 +44,16 @@ impl DiagnosticsManager {
         analyze_latex_chktex(workspace, &mut self.chktex_diagnostics, &uri, options);
     }
 
+    /// Forgets all diagnostics of a document that has been removed from the workspace.
+    pub fn remove(&mut self, uri: &Uri) {
+        self.static_diagnostics.remove(uri);
+        for diagnostics_by_uri in self.static_diagnostics.values_mut() {
+            diagnostics_by_uri.remove(uri);
+        }
+
+        self.chktex_diagnostics.remove(uri);
+    }
+
     pub fn publish(&self, uri: Arc<Uri>) -> Vec<Diagnostic> {
         let mut all_diagnostics = Vec::new();
         for diagnostics_by_uri in self.static_diagnostics.values() {
@@ -52,4 +68,86 @@ impl DiagnosticsManager {
 
         all_diagnostics
     }
+
+    pub fn pull_document(
+        &mut self,
+        workspace: &dyn Workspace,
+        uri: Arc<Uri>,
+        previous_result_id: Option<&str>,
+    ) -> DocumentDiagnosticReport {
+        self.update_static(workspace, Arc::clone(&uri));
+        create_report(self.publish(uri), previous_result_id)
+    }
+
+    /// Analyzes all documents of the workspace and creates their reports.
+    /// The manager is only locked while storing and collecting the diagnostics
+    /// so that the analysis does not block the other diagnostics handlers.
+    pub fn pull_workspace(
+        manager: &Mutex<Self>,
+        workspace: &dyn Workspace,
+        previous_result_ids: &FxHashMap<Uri, String>,
+    ) -> Vec<WorkspaceDocumentDiagnosticReport> {
+        let documents = workspace.documents();
+        let static_diagnostics: Vec<_> = documents
+            .iter()
+            .map(|document| analyze_static(workspace, &document.uri))
+            .collect();
+
+        let mut manager = manager.lock().unwrap();
+        for (document, diagnostics_by_uri) in documents.iter().zip(static_diagnostics) {
+            manager
+                .static_diagnostics
+                .insert(Arc::clone(&document.uri), diagnostics_by_uri);
+        }
+
+        let all_diagnostics: Vec<_> = documents
+            .iter()
+            .map(|document| manager.publish(Arc::clone(&document.uri)))
+            .collect();
+        drop(manager);
+
+        documents
+            .into_iter()
+            .zip(all_diagnostics)
+            .map(|(document, diagnostics)| {
+                let previous_result_id = previous_result_ids
+                    .get(document.uri.as_ref())
+                    .map(|id| id.as_str());
+
+                WorkspaceDocumentDiagnosticReport {
+                    uri: document.uri.as_ref().clone().into(),
+                    version: None,
+                    report: create_report(diagnostics, previous_result_id),
+                }
+            })
+            .collect()
+    }
+}
+
+fn analyze_static(workspace: &dyn Workspace, uri: &Uri) -> MultiMap<Arc<Uri>, Diagnostic> {
+    let mut diagnostics_by_uri = MultiMap::new();
+    analyze_build_log_static(workspace, &mut diagnostics_by_uri, uri);
+    analyze_bibtex_static(workspace, &mut diagnostics_by_uri, uri);
+    analyze_latex_static(workspace, &mut diagnostics_by_uri, uri);
+    diagnostics_by_uri
+}
+
+fn create_report(
+    diagnostics: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> DocumentDiagnosticReport {
+    let mut hasher = FxHasher::default();
+    serde_json::to_string(&diagnostics)
+        .unwrap()
+        .hash(&mut hasher);
+    let result_id = format!("{:x}", hasher.finish());
+
+    if previous_result_id == Some(result_id.as_str()) {
+        DocumentDiagnosticReport::Unchanged { result_id }
+    } else {
+        DocumentDiagnosticReport::Full {
+            result_id,
+            items: diagnostics,
+        }
+    }
 }
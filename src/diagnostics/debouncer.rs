@@ -37,14 +37,10 @@ impl DiagnosticsDebouncer {
                 document,
             }) = receiver.recv()
             {
-                let delay = {
-                    context
-                        .options
-                        .read()
-                        .unwrap()
-                        .diagnostics_delay
-                        .unwrap_or(300)
-                };
+                let delay = context
+                    .options_for(&document.uri)
+                    .diagnostics_delay
+                    .unwrap_or(300);
 
                 if let Some(time) = last_task_time_by_uri.get(&document.uri) {
                     if time.elapsed().as_millis() < delay as u128 {
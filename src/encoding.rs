@@ -0,0 +1,115 @@
+use anyhow::{bail, Result};
+use encoding_rs::{Encoding, UTF_8};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static MAGIC_COMMENT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?im)^\s*%\s*!\s*TeX\s+encoding\s*=\s*([^\s]+)").unwrap());
+
+static INPUTENC_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\\usepackage\s*\[([^\]]*)\]\s*\{inputenc\}").unwrap());
+
+/// Detects the encoding of a TeX source file from a `% !TEX encoding = ...` magic comment
+/// or the options of the `inputenc` package.
+/// Falls back to the given default encoding (or UTF-8) if there is no declaration.
+pub fn detect_encoding(text: &str, default_encoding: Option<&str>) -> &'static Encoding {
+    MAGIC_COMMENT_REGEX
+        .captures(text)
+        .and_then(|captures| encoding_by_name(&captures[1]))
+        .or_else(|| {
+            INPUTENC_REGEX.captures(text).and_then(|captures| {
+                captures[1]
+                    .split(',')
+                    .filter_map(|option| encoding_by_name(option.trim()))
+                    .last()
+            })
+        })
+        .or_else(|| default_encoding.and_then(encoding_by_name))
+        .unwrap_or(UTF_8)
+}
+
+fn encoding_by_name(name: &str) -> Option<&'static Encoding> {
+    let label = match name.to_lowercase().as_str() {
+        "ansinew" => "windows-1252",
+        "applemac" => "macintosh",
+        "latin9" => "iso-8859-15",
+        "latin10" => "iso-8859-16",
+        "utf8x" => "utf-8",
+        _ => name,
+    };
+
+    Encoding::for_label(label.as_bytes())
+}
+
+/// Decodes the contents of a TeX source file using the detected encoding.
+pub fn decode(data: &[u8], default_encoding: Option<&str>) -> String {
+    let encoding = detect_encoding(&String::from_utf8_lossy(data), default_encoding);
+    let (text, _) = encoding.decode_with_bom_removal(data);
+    text.into_owned()
+}
+
+/// Encodes the contents of a TeX source file using the encoding declared in the text.
+/// Fails if the text contains characters that cannot be represented in this encoding.
+pub fn encode(text: &str, default_encoding: Option<&str>) -> Result<Vec<u8>> {
+    let encoding = detect_encoding(text, default_encoding);
+    let (data, _, had_unmappable_characters) = encoding.encode(text);
+    if had_unmappable_characters {
+        bail!(
+            "The text contains characters that cannot be encoded in {}",
+            encoding.name()
+        );
+    }
+
+    Ok(data.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{ISO_8859_15, WINDOWS_1250, WINDOWS_1252};
+
+    use super::*;
+
+    #[test]
+    fn test_detect_magic_comment() {
+        let text = "% !TEX encoding = ISO-8859-15\n\\documentclass{article}";
+        assert_eq!(detect_encoding(text, None), ISO_8859_15);
+    }
+
+    #[test]
+    fn test_detect_inputenc() {
+        let text = "\\usepackage[latin1]{inputenc}";
+        assert_eq!(detect_encoding(text, None), WINDOWS_1252);
+    }
+
+    #[test]
+    fn test_detect_inputenc_multiple_options() {
+        let text = "\\usepackage[utf8,cp1250]{inputenc}";
+        assert_eq!(detect_encoding(text, None), WINDOWS_1250);
+    }
+
+    #[test]
+    fn test_detect_default() {
+        assert_eq!(detect_encoding("foo", Some("latin1")), WINDOWS_1252);
+        assert_eq!(detect_encoding("foo", None), UTF_8);
+    }
+
+    #[test]
+    fn test_decode_encode_latin1() {
+        let data = b"\\usepackage[latin1]{inputenc}\n\xe4\xf6\xfc";
+        let text = decode(data, None);
+        assert_eq!(text, "\\usepackage[latin1]{inputenc}\näöü");
+        assert_eq!(encode(&text, None).unwrap(), data.to_vec());
+    }
+
+    #[test]
+    fn test_encode_unmappable_character() {
+        let text = "\\usepackage[latin1]{inputenc}\n\u{4e2d}";
+        assert!(encode(text, None).is_err());
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        let text = decode("äöü".as_bytes(), None);
+        assert_eq!(text, "äöü");
+    }
+}
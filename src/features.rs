@@ -1,40 +1,68 @@
 mod build;
+mod code_action;
+mod code_lens;
+mod color;
+mod command;
 #[cfg(feature = "completion")]
 mod completion;
 mod cursor;
 mod definition;
+mod dependency_graph;
 mod folding;
 mod formatting;
 mod forward_search;
 mod highlight;
 mod hover;
+mod inlay_hint;
 mod link;
+mod linked_editing;
 mod lsp_kinds;
 mod reference;
 mod rename;
+mod selection;
 #[cfg(feature = "semantic")]
 mod semantic;
+mod signature;
 mod symbol;
 
 use std::sync::Arc;
 
-use crate::{Document, ServerContext, Workspace, WorkspaceSubset};
+use crate::{Document, Options, ServerContext, Workspace, WorkspaceSubset};
 
 #[cfg(feature = "completion")]
 pub use self::completion::{complete, CompletionItemData, COMPLETION_LIMIT};
 #[cfg(feature = "semantic")]
-pub use self::semantic::{find_semantic_tokens_range, legend};
+pub use self::semantic::{
+    find_semantic_tokens_full, find_semantic_tokens_full_delta, find_semantic_tokens_range, legend,
+    SemanticTokensCache,
+};
 pub use self::{
     build::{BuildEngine, BuildParams, BuildResult, BuildStatus},
+    code_action::find_code_actions,
+    code_lens::{find_code_lenses, resolve_code_lens, CodeLensData},
+    color::{find_color_presentations, find_document_colors},
+    command::{
+        change_environment, clean_auxiliary, show_dependency_graph, ChangeEnvironmentParams,
+        BUILD_COMMAND, CHANGE_ENVIRONMENT_COMMAND, CLEAN_AUXILIARY_COMMAND, COMMANDS,
+        FORWARD_SEARCH_COMMAND, SHOW_DEPENDENCY_GRAPH_COMMAND,
+    },
     definition::goto_definition,
+    dependency_graph::{
+        build_dependency_graph, DependencyEdge, DependencyGraph, DependencyGraphParams,
+        DependencyKind, DependencyNode, GraphFormat,
+    },
     folding::find_foldings,
-    formatting::format_source_code,
+    formatting::{format_source_code, format_source_code_on_type, format_source_code_range},
     forward_search::{execute_forward_search, ForwardSearchResult, ForwardSearchStatus},
     highlight::find_document_highlights,
     hover::find_hover,
+    inlay_hint::{find_inlay_hints, InlayHint, InlayHintParams},
     link::find_document_links,
+    linked_editing::find_linked_editing_ranges,
     reference::find_all_references,
     rename::{prepare_rename_all, rename_all},
+    selection::find_selection_ranges,
+    signature::find_signature_help,
     symbol::{find_document_symbols, find_workspace_symbols},
 };
 
@@ -50,24 +78,32 @@ impl<P> FeatureRequest<P> {
     pub fn main_document(&self) -> &Document {
         &self.subset.documents[0]
     }
+
+    pub fn options(&self) -> Options {
+        self.context.options_for(&self.main_document().uri)
+    }
 }
 
 #[cfg(test)]
 mod testing {
     use std::{path::PathBuf, sync::Arc};
 
+    use cstree::{TextRange, TextSize};
     use lsp_types::{
-        ClientCapabilities, ClientInfo, CompletionParams, DocumentFormattingParams,
-        DocumentHighlightParams, DocumentLinkParams, FoldingRangeParams, FormattingOptions,
-        GotoDefinitionParams, HoverParams, PartialResultParams, Position, ReferenceContext,
-        ReferenceParams, RenameParams, TextDocumentIdentifier, TextDocumentPositionParams,
-        WorkDoneProgressParams,
+        ClientCapabilities, ClientInfo, CodeActionContext, CodeActionParams, CodeLens,
+        CodeLensParams, Color, ColorPresentationParams, CompletionParams, Diagnostic,
+        DocumentColorParams, DocumentFormattingParams, DocumentHighlightParams, DocumentLinkParams,
+        DocumentOnTypeFormattingParams, DocumentRangeFormattingParams, FoldingRangeParams,
+        FormattingOptions, GotoDefinitionParams, HoverParams, LinkedEditingRangeParams,
+        PartialResultParams, Position, Range, ReferenceContext, ReferenceParams, RenameParams,
+        SelectionRangeParams, SemanticTokensDeltaParams, SemanticTokensParams, SignatureHelpParams,
+        TextDocumentIdentifier, TextDocumentPositionParams, WorkDoneProgressParams,
     };
     use typed_builder::TypedBuilder;
 
     use crate::{
-        create_workspace_fast, distro::Resolver, DocumentLanguage, Options, ServerContext, Uri,
-        Workspace, WorkspaceSource,
+        create_workspace_fast, distro::Resolver, DocumentLanguage, LineIndex, LineIndexExt,
+        Options, ServerContext, Uri, Workspace, WorkspaceSource,
     };
 
     use super::*;
@@ -90,6 +126,9 @@ mod testing {
         #[builder(default)]
         include_declaration: bool,
 
+        #[builder(default)]
+        diagnostics: Vec<Diagnostic>,
+
         #[builder(default)]
         client_capabilities: ClientCapabilities,
 
@@ -248,6 +287,17 @@ mod testing {
             self.request(params)
         }
 
+        pub fn change_environment(self) -> FeatureRequest<ChangeEnvironmentParams> {
+            let params = ChangeEnvironmentParams {
+                text_document_position: TextDocumentPositionParams::new(
+                    self.identifier(),
+                    Position::new(self.line, self.character),
+                ),
+                new_name: self.new_name.to_string(),
+            };
+            self.request(params)
+        }
+
         pub fn formatting(self) -> FeatureRequest<DocumentFormattingParams> {
             let params = DocumentFormattingParams {
                 text_document: self.identifier(),
@@ -257,6 +307,31 @@ mod testing {
             self.request(params)
         }
 
+        pub fn range_formatting(self) -> FeatureRequest<DocumentRangeFormattingParams> {
+            let params = DocumentRangeFormattingParams {
+                text_document: self.identifier(),
+                range: Range::new(
+                    Position::new(self.line, self.character),
+                    Position::new(self.line, self.character),
+                ),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                options: FormattingOptions::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn on_type_formatting(self) -> FeatureRequest<DocumentOnTypeFormattingParams> {
+            let params = DocumentOnTypeFormattingParams {
+                text_document_position: TextDocumentPositionParams::new(
+                    self.identifier(),
+                    Position::new(self.line, self.character),
+                ),
+                ch: "}".into(),
+                options: FormattingOptions::default(),
+            };
+            self.request(params)
+        }
+
         pub fn highlight(self) -> FeatureRequest<DocumentHighlightParams> {
             let params = DocumentHighlightParams {
                 text_document_position_params: TextDocumentPositionParams::new(
@@ -268,5 +343,130 @@ mod testing {
             };
             self.request(params)
         }
+
+        pub fn signature_help(self) -> FeatureRequest<SignatureHelpParams> {
+            let params = SignatureHelpParams {
+                context: None,
+                text_document_position_params: TextDocumentPositionParams::new(
+                    self.identifier(),
+                    Position::new(self.line, self.character),
+                ),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn inlay_hint(self) -> FeatureRequest<InlayHintParams> {
+            let (_, text) = self
+                .files
+                .iter()
+                .find(|(name, _)| *name == self.main)
+                .unwrap();
+            let text = text.trim();
+            let range =
+                LineIndex::new(text).line_col_lsp_range(TextRange::up_to(TextSize::of(text)));
+            let params = InlayHintParams {
+                text_document: self.identifier(),
+                range,
+            };
+            self.request(params)
+        }
+
+        pub fn semantic_tokens_full(self) -> FeatureRequest<SemanticTokensParams> {
+            let params = SemanticTokensParams {
+                text_document: self.identifier(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn semantic_tokens_full_delta(
+            self,
+            previous_result_id: String,
+        ) -> FeatureRequest<SemanticTokensDeltaParams> {
+            let params = SemanticTokensDeltaParams {
+                text_document: self.identifier(),
+                previous_result_id,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn code_lens(self) -> FeatureRequest<CodeLensParams> {
+            let params = CodeLensParams {
+                text_document: self.identifier(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn code_lens_resolve(self, code_lens: CodeLens) -> FeatureRequest<CodeLens> {
+            self.request(code_lens)
+        }
+
+        pub fn document_color(self) -> FeatureRequest<DocumentColorParams> {
+            let params = DocumentColorParams {
+                text_document: self.identifier(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn color_presentation(
+            self,
+            range: Range,
+            color: Color,
+        ) -> FeatureRequest<ColorPresentationParams> {
+            let params = ColorPresentationParams {
+                text_document: self.identifier(),
+                color,
+                range,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn linked_editing_range(self) -> FeatureRequest<LinkedEditingRangeParams> {
+            let params = LinkedEditingRangeParams {
+                text_document_position_params: TextDocumentPositionParams::new(
+                    self.identifier(),
+                    Position::new(self.line, self.character),
+                ),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn selection_range(self) -> FeatureRequest<SelectionRangeParams> {
+            let params = SelectionRangeParams {
+                text_document: self.identifier(),
+                positions: vec![Position::new(self.line, self.character)],
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            self.request(params)
+        }
+
+        pub fn code_action(self) -> FeatureRequest<CodeActionParams> {
+            let params = CodeActionParams {
+                text_document: self.identifier(),
+                range: Range::new(
+                    Position::new(self.line, self.character),
+                    Position::new(self.line, self.character),
+                ),
+                context: CodeActionContext {
+                    diagnostics: self.diagnostics.clone(),
+                    only: None,
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+            self.request(params)
+        }
     }
 }
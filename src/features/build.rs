@@ -1,13 +1,13 @@
 use std::{
-    io::{BufRead, BufReader, Read},
+    io::{self, BufRead, BufReader, Read},
     path::Path,
-    process::{Command, Stdio},
+    process::{Child, Command, ExitStatus, Stdio},
     sync::{Arc, Mutex},
     thread::{self, JoinHandle},
 };
 
 use anyhow::Result;
-use cancellation::CancellationToken;
+use cancellation::{CancellationToken, CancellationTokenSource};
 use chashmap::CHashMap;
 use crossbeam_channel::Sender;
 use encoding_rs_io::DecodeReaderBytesBuilder;
@@ -21,7 +21,7 @@ use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use uuid::Uuid;
 
-use crate::{client, req_queue::ReqQueue, ClientCapabilitiesExt, DocumentLanguage, Uri};
+use crate::{client, req_queue::ReqQueue, ClientCapabilitiesExt, Document, DocumentLanguage, Uri};
 
 use super::{forward_search, FeatureRequest};
 
@@ -71,7 +71,7 @@ impl<'a> ProgressReporter<'a> {
                         WorkDoneProgressBegin {
                             title: "Building".to_string(),
                             message: Some(uri.as_str().to_string()),
-                            cancellable: Some(false),
+                            cancellable: Some(true),
                             percentage: None,
                         },
                     )),
@@ -102,9 +102,17 @@ impl<'a> Drop for ProgressReporter<'a> {
 pub struct BuildEngine {
     lock: Mutex<()>,
     pub positions_by_uri: CHashMap<Arc<Uri>, Position>,
+    progress_token_sources: CHashMap<String, Arc<CancellationTokenSource>>,
 }
 
 impl BuildEngine {
+    /// Cancels the build that reports its progress with the given token.
+    pub fn cancel(&self, progress_token: &str) {
+        if let Some(token_source) = self.progress_token_sources.get(progress_token) {
+            token_source.cancel();
+        }
+    }
+
     pub fn build(
         &self,
         request: FeatureRequest<BuildParams>,
@@ -113,20 +121,13 @@ impl BuildEngine {
         lsp_sender: &Sender<lsp_server::Message>,
     ) -> Result<BuildResult> {
         let lock = self.lock.lock().unwrap();
+        if cancellation_token.is_canceled() {
+            return Ok(BuildResult {
+                status: BuildStatus::CANCELLED,
+            });
+        }
 
-        let document = request
-            .subset
-            .documents
-            .iter()
-            .find(|document| {
-                if let Some(data) = document.data.as_latex() {
-                    data.extras.has_document_environment
-                } else {
-                    false
-                }
-            })
-            .map(|document| document.as_ref())
-            .unwrap_or_else(|| request.main_document());
+        let document = find_build_document(&request);
 
         if document.language() != DocumentLanguage::Latex {
             return Ok(BuildResult {
@@ -139,7 +140,6 @@ impl BuildEngine {
                 status: BuildStatus::FAILURE,
             });
         }
-        let path = document.uri.to_file_path().unwrap();
 
         let supports_progress = {
             request
@@ -151,51 +151,34 @@ impl BuildEngine {
         };
 
         let token = format!("texlab-build-{}", Uuid::new_v4());
+        let progress_token_source = Arc::new(CancellationTokenSource::new());
+        self.progress_token_sources
+            .insert(token.clone(), Arc::clone(&progress_token_source));
+
         let progress_reporter = ProgressReporter {
             supports_progress,
             req_queue,
             lsp_sender: lsp_sender.clone(),
             token: &token,
         };
-        progress_reporter.start(&document.uri)?;
-
-        let options = { request.context.options.read().unwrap().clone() };
-
-        let build_dir = options
-            .root_directory
-            .as_ref()
-            .map(AsRef::as_ref)
-            .or_else(|| path.parent())
-            .unwrap();
-
-        let args: Vec<_> = options
-            .build
-            .args()
-            .into_iter()
-            .map(|arg| replace_placeholder(arg, &path))
-            .collect();
-
-        let mut process = Command::new(options.build.executable())
-            .args(args)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .current_dir(build_dir)
-            .spawn()?;
-
-        let log_handle = capture_output(&mut process, lsp_sender);
-        let success = process.wait().map(|status| status.success())?;
-        log_handle.join().unwrap();
-        let status = if success {
-            BuildStatus::SUCCESS
-        } else {
-            BuildStatus::ERROR
-        };
 
+        let result = progress_reporter.start(&document.uri).and_then(|_| {
+            run_build(
+                &request,
+                document,
+                &[cancellation_token, progress_token_source.token().as_ref()],
+                lsp_sender,
+            )
+        });
+
+        self.progress_token_sources.remove(&token);
         drop(progress_reporter);
         drop(lock);
 
-        if options.build.forward_search_after {
+        let status = result?;
+        let forward_search_after = request.options().build.forward_search_after;
+
+        if status != BuildStatus::CANCELLED && forward_search_after {
             let request = FeatureRequest {
                 params: TextDocumentPositionParams {
                     position: self
@@ -218,6 +201,133 @@ impl BuildEngine {
     }
 }
 
+fn run_build(
+    request: &FeatureRequest<BuildParams>,
+    document: &Document,
+    cancellation_tokens: &[&CancellationToken],
+    lsp_sender: &Sender<lsp_server::Message>,
+) -> Result<BuildStatus> {
+    let path = document.uri.to_file_path().unwrap();
+    let options = request.context.options_for(&document.uri);
+
+    let build_dir = options
+        .root_directory
+        .as_ref()
+        .map(AsRef::as_ref)
+        .or_else(|| path.parent())
+        .unwrap();
+
+    let args: Vec<_> = options
+        .build
+        .args()
+        .into_iter()
+        .map(|arg| replace_placeholder(arg, &path))
+        .collect();
+
+    let mut command = Command::new(options.build.executable());
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .current_dir(build_dir);
+
+    // The build runs in its own process group so that it can be cancelled
+    // together with the processes it spawns (for example, `latexmk` spawns `pdflatex`).
+    #[cfg(unix)]
+    unsafe {
+        use std::os::unix::process::CommandExt;
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) == 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        });
+    }
+
+    let mut process = command.spawn()?;
+    let log_handle = capture_output(&mut process, lsp_sender);
+    let exit_status = wait_for_build(&mut process, cancellation_tokens);
+    log_handle.join().unwrap();
+
+    let status = if cancellation_tokens.iter().any(|token| token.is_canceled()) {
+        BuildStatus::CANCELLED
+    } else if exit_status?.success() {
+        BuildStatus::SUCCESS
+    } else {
+        BuildStatus::ERROR
+    };
+    Ok(status)
+}
+
+/// Blocks until the build process exits.
+/// Cancelling one of the tokens kills the process tree, which ends the wait.
+fn wait_for_build(
+    process: &mut Child,
+    cancellation_tokens: &[&CancellationToken],
+) -> io::Result<ExitStatus> {
+    fn wait(
+        process: &mut Child,
+        all_tokens: &[&CancellationToken],
+        tokens: &[&CancellationToken],
+    ) -> io::Result<ExitStatus> {
+        match tokens.split_first() {
+            Some((token, other_tokens)) => {
+                let pid = process.id();
+                token.run(
+                    move || kill_process_tree(pid),
+                    || wait(process, all_tokens, other_tokens),
+                )
+            }
+            None => {
+                // The build may have been cancelled before the handlers were registered.
+                if all_tokens.iter().any(|token| token.is_canceled()) {
+                    kill_process_tree(process.id());
+                }
+                process.wait()
+            }
+        }
+    }
+
+    wait(process, cancellation_tokens, cancellation_tokens)
+}
+
+pub(super) fn find_build_document<P>(request: &FeatureRequest<P>) -> &Document {
+    request
+        .subset
+        .documents
+        .iter()
+        .find(|document| {
+            if let Some(data) = document.data.as_latex() {
+                data.extras.has_document_environment
+            } else {
+                false
+            }
+        })
+        .map(|document| document.as_ref())
+        .unwrap_or_else(|| request.main_document())
+}
+
+/// Terminates the process group of the build,
+/// which contains the processes spawned by the build command.
+fn kill_process_tree(pid: u32) {
+    #[cfg(windows)]
+    {
+        let _ = Command::new("taskkill")
+            .args(&["/PID", &pid.to_string(), "/T", "/F"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
 fn capture_output(
     process: &mut std::process::Child,
     lsp_sender: &Sender<lsp_server::Message>,
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use cancellation::CancellationToken;
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, Diagnostic, NumberOrString,
+    Range, TextEdit, WorkspaceEdit,
+};
+
+use crate::{
+    syntax::{latex, CstNode},
+    Document, LineIndexExt,
+};
+
+use super::FeatureRequest;
+
+pub fn find_code_actions(
+    request: FeatureRequest<CodeActionParams>,
+    token: &CancellationToken,
+) -> Vec<CodeActionOrCommand> {
+    let mut actions = Vec::new();
+    let main_document = request.main_document();
+    for diagnostic in &request.params.context.diagnostics {
+        if token.is_canceled() {
+            break;
+        }
+
+        if diagnostic.source.as_deref() != Some("texlab") {
+            continue;
+        }
+
+        match &diagnostic.code {
+            Some(NumberOrString::Number(1)) => {
+                actions.extend(remove_unexpected_curly(main_document, diagnostic));
+            }
+            Some(NumberOrString::Number(2)) => {
+                actions.push(insert_missing_curly(main_document, diagnostic));
+            }
+            Some(NumberOrString::Number(3)) => {
+                actions.extend(fix_mismatched_environment(main_document, diagnostic));
+            }
+            _ => {}
+        };
+    }
+
+    actions
+        .into_iter()
+        .map(CodeActionOrCommand::CodeAction)
+        .collect()
+}
+
+fn remove_unexpected_curly(document: &Document, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    let range = document.line_index.offset_lsp_range(diagnostic.range);
+    if document
+        .text
+        .get(usize::from(range.start())..usize::from(range.end()))?
+        != "}"
+    {
+        return None;
+    }
+
+    Some(create_quick_fix(
+        "Remove unexpected \"}\"".to_string(),
+        document,
+        diagnostic,
+        vec![TextEdit::new(diagnostic.range, String::new())],
+    ))
+}
+
+fn insert_missing_curly(document: &Document, diagnostic: &Diagnostic) -> CodeAction {
+    let position = diagnostic.range.end;
+    create_quick_fix(
+        "Insert missing \"}\"".to_string(),
+        document,
+        diagnostic,
+        vec![TextEdit::new(
+            Range::new(position, position),
+            "}".to_string(),
+        )],
+    )
+}
+
+fn fix_mismatched_environment(document: &Document, diagnostic: &Diagnostic) -> Vec<CodeAction> {
+    let data = match document.data.as_latex() {
+        Some(data) => data,
+        None => return Vec::new(),
+    };
+
+    let range = document.line_index.offset_lsp_range(diagnostic.range);
+    data.root
+        .descendants()
+        .filter_map(latex::Environment::cast)
+        .find_map(|environment| {
+            let name1 = environment.begin()?.name()?.key()?;
+            let name2 = environment.end()?.name()?.key()?;
+            if name1.small_range() != range {
+                return None;
+            }
+
+            let range1 = document.line_index.line_col_lsp_range(name1.small_range());
+            let range2 = document.line_index.line_col_lsp_range(name2.small_range());
+            let text1 = &document.text[name1.small_range()];
+            let text2 = &document.text[name2.small_range()];
+
+            Some(vec![
+                CodeAction {
+                    is_preferred: Some(true),
+                    ..create_quick_fix(
+                        format!("Change \\end{{{}}} to \\end{{{}}}", text2, text1),
+                        document,
+                        diagnostic,
+                        vec![TextEdit::new(range2, text1.to_string())],
+                    )
+                },
+                create_quick_fix(
+                    format!("Change \\begin{{{}}} to \\begin{{{}}}", text1, text2),
+                    document,
+                    diagnostic,
+                    vec![TextEdit::new(range1, text2.to_string())],
+                ),
+            ])
+        })
+        .unwrap_or_default()
+}
+
+fn create_quick_fix(
+    title: String,
+    document: &Document,
+    diagnostic: &Diagnostic,
+    edits: Vec<TextEdit>,
+) -> CodeAction {
+    let mut changes = HashMap::new();
+    changes.insert(document.uri.as_ref().clone().into(), edits);
+    CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit::new(changes)),
+        ..CodeAction::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::DiagnosticSeverity;
+
+    use crate::{features::testing::FeatureTester, RangeExt};
+
+    use super::*;
+
+    fn create_diagnostic(code: i32, range: Range) -> Diagnostic {
+        Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::Error),
+            code: Some(NumberOrString::Number(code)),
+            code_description: None,
+            source: Some("texlab".to_string()),
+            message: String::new(),
+            related_information: None,
+            tags: None,
+            data: None,
+        }
+    }
+
+    fn find_edits(action: &CodeActionOrCommand) -> Vec<TextEdit> {
+        match action {
+            CodeActionOrCommand::CodeAction(action) => action
+                .edit
+                .as_ref()
+                .and_then(|edit| edit.changes.as_ref())
+                .map(|changes| changes.values().flatten().cloned().collect())
+                .unwrap_or_default(),
+            CodeActionOrCommand::Command(_) => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .build()
+            .code_action();
+
+        let actual_actions = find_code_actions(request, CancellationToken::none());
+        assert!(actual_actions.is_empty());
+    }
+
+    #[test]
+    fn test_foreign_diagnostic() {
+        let mut diagnostic = create_diagnostic(1, Range::new_simple(0, 0, 0, 1));
+        diagnostic.source = Some("chktex".to_string());
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "}")])
+            .main("main.tex")
+            .diagnostics(vec![diagnostic])
+            .build()
+            .code_action();
+
+        let actual_actions = find_code_actions(request, CancellationToken::none());
+        assert!(actual_actions.is_empty());
+    }
+
+    #[test]
+    fn test_unexpected_curly() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\foo}"#)])
+            .main("main.tex")
+            .diagnostics(vec![create_diagnostic(1, Range::new_simple(0, 4, 0, 5))])
+            .build()
+            .code_action();
+
+        let actual_actions = find_code_actions(request, CancellationToken::none());
+        assert_eq!(actual_actions.len(), 1);
+        assert_eq!(
+            find_edits(&actual_actions[0]),
+            vec![TextEdit::new(Range::new_simple(0, 4, 0, 5), String::new())]
+        );
+    }
+
+    #[test]
+    fn test_missing_curly() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\foo{bar"#)])
+            .main("main.tex")
+            .diagnostics(vec![create_diagnostic(2, Range::new_simple(0, 8, 0, 8))])
+            .build()
+            .code_action();
+
+        let actual_actions = find_code_actions(request, CancellationToken::none());
+        assert_eq!(actual_actions.len(), 1);
+        assert_eq!(
+            find_edits(&actual_actions[0]),
+            vec![TextEdit::new(
+                Range::new_simple(0, 8, 0, 8),
+                "}".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_mismatched_environment() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\begin{foo}\end{bar}"#)])
+            .main("main.tex")
+            .diagnostics(vec![create_diagnostic(3, Range::new_simple(0, 7, 0, 10))])
+            .build()
+            .code_action();
+
+        let actual_actions = find_code_actions(request, CancellationToken::none());
+        assert_eq!(actual_actions.len(), 2);
+        assert_eq!(
+            find_edits(&actual_actions[0]),
+            vec![TextEdit::new(
+                Range::new_simple(0, 16, 0, 19),
+                "foo".to_string()
+            )]
+        );
+        assert_eq!(
+            find_edits(&actual_actions[1]),
+            vec![TextEdit::new(
+                Range::new_simple(0, 7, 0, 10),
+                "bar".to_string()
+            )]
+        );
+    }
+}
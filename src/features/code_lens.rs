@@ -0,0 +1,246 @@
+use cancellation::CancellationToken;
+use lsp_types::{
+    CodeLens, CodeLensParams, Command, PartialResultParams, ReferenceContext, ReferenceParams,
+    TextDocumentIdentifier, TextDocumentPositionParams, Url, WorkDoneProgressParams,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    syntax::{bibtex, latex, CstNode},
+    DocumentData, LineIndexExt, Uri,
+};
+
+use super::{find_all_references, FeatureRequest};
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeLensData {
+    pub uri: Uri,
+}
+
+pub fn find_code_lenses(
+    request: FeatureRequest<CodeLensParams>,
+    token: &CancellationToken,
+) -> Vec<CodeLens> {
+    let main_document = request.main_document();
+    let mut ranges = Vec::new();
+    match &main_document.data {
+        DocumentData::Latex(data) => {
+            for node in data.root.descendants() {
+                if token.is_canceled() {
+                    break;
+                }
+
+                if let Some(range) = latex::LabelDefinition::cast(node)
+                    .and_then(|label| label.name())
+                    .and_then(|name| name.key())
+                    .map(|name| name.small_range())
+                    .or_else(|| {
+                        latex::CommandDefinition::cast(node)
+                            .and_then(|def| def.name())
+                            .and_then(|name| name.command())
+                            .map(|name| name.text_range())
+                    })
+                {
+                    ranges.push(range);
+                }
+            }
+        }
+        DocumentData::Bibtex(data) => {
+            for entry in data.root.children().filter_map(bibtex::Entry::cast) {
+                if token.is_canceled() {
+                    break;
+                }
+
+                if let Some(key) = entry.key() {
+                    ranges.push(key.small_range());
+                }
+            }
+        }
+        DocumentData::BuildLog(_) => {}
+    }
+
+    let data = serde_json::to_value(CodeLensData {
+        uri: main_document.uri.as_ref().clone(),
+    })
+    .unwrap();
+
+    ranges
+        .into_iter()
+        .map(|range| CodeLens {
+            range: main_document.line_index.line_col_lsp_range(range),
+            command: None,
+            data: Some(data.clone()),
+        })
+        .collect()
+}
+
+pub fn resolve_code_lens(request: FeatureRequest<CodeLens>, token: &CancellationToken) -> CodeLens {
+    let mut code_lens = request.params.clone();
+    let uri: Url = request.main_document().uri.as_ref().clone().into();
+    let position = code_lens.range.start;
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri.clone()),
+            position,
+        ),
+        context: ReferenceContext {
+            include_declaration: false,
+        },
+        work_done_progress_params: WorkDoneProgressParams::default(),
+        partial_result_params: PartialResultParams::default(),
+    };
+
+    let references = find_all_references(
+        FeatureRequest {
+            context: request.context,
+            params,
+            workspace: request.workspace,
+            subset: request.subset,
+        },
+        token,
+    )
+    .unwrap_or_default();
+
+    let title = match references.len() {
+        1 => "1 reference".to_string(),
+        count => format!("{} references", count),
+    };
+
+    // The arguments match the built-in command of VS Code,
+    // which other clients can register as well.
+    code_lens.command = Some(Command {
+        title,
+        command: "editor.action.showReferences".to_string(),
+        arguments: Some(vec![
+            serde_json::to_value(uri).unwrap(),
+            serde_json::to_value(position).unwrap(),
+            serde_json::to_value(references).unwrap(),
+        ]),
+    });
+
+    code_lens
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use lsp_types::Range;
+
+    use crate::{features::testing::FeatureTester, RangeExt};
+
+    use super::*;
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .build()
+            .code_lens();
+
+        let actual_lenses = find_code_lenses(request, CancellationToken::none());
+        assert!(actual_lenses.is_empty());
+    }
+
+    #[test]
+    fn test_empty_bibtex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.bib", "")])
+            .main("main.bib")
+            .build()
+            .code_lens();
+
+        let actual_lenses = find_code_lenses(request, CancellationToken::none());
+        assert!(actual_lenses.is_empty());
+    }
+
+    #[test]
+    fn test_latex() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                indoc! {r#"
+                    \newcommand{\foo}{bar}
+                    \label{baz}
+                "#},
+            )])
+            .main("main.tex")
+            .build()
+            .code_lens();
+
+        let actual_ranges: Vec<_> = find_code_lenses(request, CancellationToken::none())
+            .into_iter()
+            .map(|lens| lens.range)
+            .collect();
+
+        assert_eq!(
+            actual_ranges,
+            vec![
+                Range::new_simple(0, 12, 0, 16),
+                Range::new_simple(1, 7, 1, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bibtex() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.bib", r#"@article{foo, author = {bar}}"#)])
+            .main("main.bib")
+            .build()
+            .code_lens();
+
+        let actual_ranges: Vec<_> = find_code_lenses(request, CancellationToken::none())
+            .into_iter()
+            .map(|lens| lens.range)
+            .collect();
+
+        assert_eq!(actual_ranges, vec![Range::new_simple(0, 9, 0, 12)]);
+    }
+
+    #[test]
+    fn test_resolve_label() {
+        let tester = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                indoc! {r#"
+                    \label{foo}
+                    \ref{foo} \cref{foo}
+                "#},
+            )])
+            .main("main.tex")
+            .build();
+
+        let code_lens = find_code_lenses(tester.clone().code_lens(), CancellationToken::none())
+            .pop()
+            .unwrap();
+        let actual_lens = resolve_code_lens(
+            tester.code_lens_resolve(code_lens),
+            CancellationToken::none(),
+        );
+
+        assert_eq!(actual_lens.command.unwrap().title, "2 references");
+    }
+
+    #[test]
+    fn test_resolve_entry() {
+        let tester = FeatureTester::builder()
+            .files(vec![
+                ("main.bib", r#"@article{foo, author = {bar}}"#),
+                ("main.tex", r#"\addbibresource{main.bib}\cite{foo}"#),
+            ])
+            .main("main.bib")
+            .build();
+
+        let code_lens = find_code_lenses(tester.clone().code_lens(), CancellationToken::none())
+            .pop()
+            .unwrap();
+        let actual_lens = resolve_code_lens(
+            tester.code_lens_resolve(code_lens),
+            CancellationToken::none(),
+        );
+
+        assert_eq!(actual_lens.command.unwrap().title, "1 reference");
+    }
+}
@@ -0,0 +1,525 @@
+use cancellation::CancellationToken;
+use cstree::TextRange;
+use lsp_types::{
+    Color, ColorInformation, ColorPresentation, ColorPresentationParams, DocumentColorParams,
+    TextEdit,
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    syntax::{
+        latex::{self, HasCurly},
+        CstNode,
+    },
+    Document, LineIndexExt,
+};
+
+use super::FeatureRequest;
+
+pub fn find_document_colors(
+    request: FeatureRequest<DocumentColorParams>,
+    token: &CancellationToken,
+) -> Vec<ColorInformation> {
+    let main_document = request.main_document();
+    if main_document.data.as_latex().is_none() {
+        return Vec::new();
+    }
+
+    let mut colors_by_name = FxHashMap::default();
+    for document in request
+        .subset
+        .documents
+        .iter()
+        .filter(|document| document.uri != main_document.uri)
+    {
+        find_colors(document, &mut colors_by_name, token);
+    }
+
+    find_colors(main_document, &mut colors_by_name, token)
+        .into_iter()
+        .map(|(range, color)| ColorInformation {
+            range: main_document.line_index.line_col_lsp_range(range),
+            color,
+        })
+        .collect()
+}
+
+/// Finds the colors of the `\definecolor` and `\colorlet` commands in the document
+/// and adds them to `colors_by_name` so that later commands can refer to them.
+fn find_colors(
+    document: &Document,
+    colors_by_name: &mut FxHashMap<String, Color>,
+    token: &CancellationToken,
+) -> Vec<(TextRange, Color)> {
+    let data = match document.data.as_latex() {
+        Some(data) => data,
+        None => return Vec::new(),
+    };
+
+    let mut colors = Vec::new();
+    for node in data.root.descendants() {
+        if token.is_canceled() {
+            break;
+        }
+
+        if let Some(definition) = latex::ColorDefinition::cast(node) {
+            let name = definition.name().and_then(|name| name.key());
+            if let Some((model, range)) = find_color_spec(definition) {
+                if let Some(color) = parse_color(&model, &document.text[range]) {
+                    if let Some(name) = name {
+                        colors_by_name.insert(name.to_string(), color);
+                    }
+                    colors.push((range, color));
+                }
+            }
+        } else if let Some((name, range)) = find_color_let(node) {
+            if let Some(color) = resolve_color(&document.text[range], colors_by_name) {
+                colors_by_name.insert(name, color);
+                colors.push((range, color));
+            }
+        }
+    }
+
+    colors
+}
+
+pub fn find_color_presentations(
+    request: FeatureRequest<ColorPresentationParams>,
+    _token: &CancellationToken,
+) -> Vec<ColorPresentation> {
+    let main_document = request.main_document();
+    let data = match main_document.data.as_latex() {
+        Some(data) => data,
+        None => return Vec::new(),
+    };
+
+    let range = main_document
+        .line_index
+        .offset_lsp_range(request.params.range);
+
+    data.root
+        .descendants()
+        .filter_map(latex::ColorDefinition::cast)
+        .filter_map(find_color_spec)
+        .find(|(_, spec_range)| *spec_range == range)
+        .and_then(|(model, _)| render_color(&model, request.params.color))
+        .map(|label| {
+            vec![ColorPresentation {
+                text_edit: Some(TextEdit::new(request.params.range, label.clone())),
+                label,
+                additional_text_edits: None,
+            }]
+        })
+        .unwrap_or_default()
+}
+
+fn find_color_spec(definition: latex::ColorDefinition) -> Option<(String, TextRange)> {
+    let model = definition.model()?.key()?.to_string();
+    let spec = definition.spec()?;
+    let range = TextRange::new(
+        spec.left_curly()?.text_range().end(),
+        spec.right_curly()?.text_range().start(),
+    );
+    Some((model, range))
+}
+
+/// Returns the name and the range of the color expression of `\colorlet{name}{expression}`.
+fn find_color_let(node: &latex::SyntaxNode) -> Option<(String, TextRange)> {
+    let command = latex::GenericCommand::cast(node)?;
+    if command.name()?.text() != "\\colorlet" {
+        return None;
+    }
+
+    let groups: Vec<_> = command
+        .syntax()
+        .children()
+        .filter_map(latex::CurlyGroup::cast)
+        .collect();
+
+    if groups.len() < 2 {
+        return None;
+    }
+
+    let name = groups[0].content_text()?;
+    let spec = groups[groups.len() - 1];
+    let range = TextRange::new(
+        spec.left_curly()?.text_range().end(),
+        spec.right_curly()?.text_range().start(),
+    );
+    Some((name, range))
+}
+
+/// Evaluates a color expression of the `xcolor` package like `red!30!foo`,
+/// which mixes 30% of `red` with 70% of `foo`.
+/// A missing color at the end of the expression stands for `white`.
+/// Complementary colors (`-red`) and functional expressions are not supported.
+fn resolve_color(text: &str, colors_by_name: &FxHashMap<String, Color>) -> Option<Color> {
+    let lookup = |name: &str| {
+        let name = name.trim();
+        colors_by_name
+            .get(name)
+            .copied()
+            .or_else(|| find_base_color(name))
+    };
+
+    let mut parts = text.split('!');
+    let mut color = lookup(parts.next()?)?;
+    while let Some(percentage) = parts.next() {
+        let percentage: f32 = percentage.trim().parse().ok()?;
+        if !(0.0..=100.0).contains(&percentage) {
+            return None;
+        }
+
+        let other = match parts.next() {
+            Some(name) => lookup(name)?,
+            None => find_base_color("white")?,
+        };
+
+        let ratio = percentage / 100.0;
+        let mix = |a: f32, b: f32| ratio * a + (1.0 - ratio) * b;
+        color = Color {
+            red: mix(color.red, other.red),
+            green: mix(color.green, other.green),
+            blue: mix(color.blue, other.blue),
+            alpha: 1.0,
+        };
+    }
+
+    Some(color)
+}
+
+/// Returns the colors that are always defined by the `xcolor` package.
+fn find_base_color(name: &str) -> Option<Color> {
+    let (red, green, blue) = match name {
+        "red" => (1.0, 0.0, 0.0),
+        "green" => (0.0, 1.0, 0.0),
+        "blue" => (0.0, 0.0, 1.0),
+        "cyan" => (0.0, 1.0, 1.0),
+        "magenta" => (1.0, 0.0, 1.0),
+        "yellow" => (1.0, 1.0, 0.0),
+        "black" => (0.0, 0.0, 0.0),
+        "white" => (1.0, 1.0, 1.0),
+        "gray" => (0.5, 0.5, 0.5),
+        "darkgray" => (0.25, 0.25, 0.25),
+        "lightgray" => (0.75, 0.75, 0.75),
+        "brown" => (0.75, 0.5, 0.25),
+        "lime" => (0.75, 1.0, 0.0),
+        "olive" => (0.5, 0.5, 0.0),
+        "orange" => (1.0, 0.5, 0.0),
+        "pink" => (1.0, 0.75, 0.75),
+        "purple" => (0.75, 0.0, 0.25),
+        "teal" => (0.0, 0.5, 0.5),
+        "violet" => (0.5, 0.0, 0.5),
+        _ => return None,
+    };
+
+    Some(Color {
+        red,
+        green,
+        blue,
+        alpha: 1.0,
+    })
+}
+
+fn parse_color(model: &str, text: &str) -> Option<Color> {
+    let (red, green, blue) = match model {
+        "rgb" => {
+            let components = parse_components(text)?;
+            if components.iter().any(|value| !(0.0..=1.0).contains(value)) {
+                return None;
+            }
+            (components[0], components[1], components[2])
+        }
+        "RGB" => {
+            let components = parse_components(text)?;
+            if components
+                .iter()
+                .any(|value| !(0.0..=255.0).contains(value) || value.fract() != 0.0)
+            {
+                return None;
+            }
+            (
+                components[0] / 255.0,
+                components[1] / 255.0,
+                components[2] / 255.0,
+            )
+        }
+        "HTML" => {
+            let text = text.trim();
+            if text.len() != 6 || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            let component = |i: usize| u8::from_str_radix(&text[i..i + 2], 16).ok();
+            (
+                f32::from(component(0)?) / 255.0,
+                f32::from(component(2)?) / 255.0,
+                f32::from(component(4)?) / 255.0,
+            )
+        }
+        _ => return None,
+    };
+
+    Some(Color {
+        red,
+        green,
+        blue,
+        alpha: 1.0,
+    })
+}
+
+fn parse_components(text: &str) -> Option<Vec<f32>> {
+    let components = text
+        .split(',')
+        .map(|component| component.trim().parse().ok())
+        .collect::<Option<Vec<f32>>>()?;
+
+    if components.len() == 3 {
+        Some(components)
+    } else {
+        None
+    }
+}
+
+fn render_color(model: &str, color: Color) -> Option<String> {
+    let components = [color.red, color.green, color.blue];
+    let text = match model {
+        "rgb" => components
+            .iter()
+            .map(|value| {
+                let text = format!("{:.3}", value);
+                let text = text.trim_end_matches('0').trim_end_matches('.');
+                text.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        "RGB" => components
+            .iter()
+            .map(|value| to_byte(*value).to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        "HTML" => components
+            .iter()
+            .map(|value| format!("{:02X}", to_byte(*value)))
+            .collect(),
+        _ => return None,
+    };
+
+    Some(text)
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Range;
+
+    use crate::{features::testing::FeatureTester, RangeExt};
+
+    use super::*;
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .build()
+            .document_color();
+
+        let actual_colors = find_document_colors(request, CancellationToken::none());
+        assert!(actual_colors.is_empty());
+    }
+
+    #[test]
+    fn test_empty_bibtex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.bib", "")])
+            .main("main.bib")
+            .build()
+            .document_color();
+
+        let actual_colors = find_document_colors(request, CancellationToken::none());
+        assert!(actual_colors.is_empty());
+    }
+
+    #[test]
+    fn test_models() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\definecolor{foo}{rgb}{1,0.5,0}\n\\definecolor{bar}{HTML}{FF8000}\n\\definecolor{baz}{RGB}{255,128,0}\n\\definecolor{qux}{cmyk}{0,0,0,1}",
+            )])
+            .main("main.tex")
+            .build()
+            .document_color();
+
+        let actual_colors = find_document_colors(request, CancellationToken::none());
+        let actual_ranges: Vec<_> = actual_colors.iter().map(|color| color.range).collect();
+        assert_eq!(
+            actual_ranges,
+            vec![
+                Range::new_simple(0, 23, 0, 30),
+                Range::new_simple(1, 24, 1, 30),
+                Range::new_simple(2, 23, 2, 32),
+            ]
+        );
+
+        for color in actual_colors {
+            assert_eq!(color.color.red, 1.0);
+            assert!((color.color.green - 0.5).abs() < 0.01);
+            assert_eq!(color.color.blue, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_invalid_spec() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\definecolor{foo}{rgb}{2,0}"#)])
+            .main("main.tex")
+            .build()
+            .document_color();
+
+        let actual_colors = find_document_colors(request, CancellationToken::none());
+        assert!(actual_colors.is_empty());
+    }
+
+    #[test]
+    fn test_presentation_rgb() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\definecolor{foo}{rgb}{1,0.5,0}"#)])
+            .main("main.tex")
+            .build()
+            .color_presentation(
+                Range::new_simple(0, 23, 0, 30),
+                Color {
+                    red: 0.25,
+                    green: 1.0,
+                    blue: 0.0,
+                    alpha: 1.0,
+                },
+            );
+
+        let actual_presentations = find_color_presentations(request, CancellationToken::none());
+        assert_eq!(actual_presentations.len(), 1);
+        assert_eq!(actual_presentations[0].label, "0.25,1,0");
+    }
+
+    #[test]
+    fn test_presentation_html() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\definecolor{foo}{HTML}{FF8000}"#)])
+            .main("main.tex")
+            .build()
+            .color_presentation(
+                Range::new_simple(0, 24, 0, 30),
+                Color {
+                    red: 0.0,
+                    green: 0.0,
+                    blue: 1.0,
+                    alpha: 1.0,
+                },
+            );
+
+        let actual_presentations = find_color_presentations(request, CancellationToken::none());
+        assert_eq!(actual_presentations.len(), 1);
+        assert_eq!(
+            actual_presentations[0].text_edit,
+            Some(TextEdit::new(
+                Range::new_simple(0, 24, 0, 30),
+                "0000FF".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_presentation_unknown_range() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\definecolor{foo}{RGB}{255,128,0}"#)])
+            .main("main.tex")
+            .build()
+            .color_presentation(
+                Range::new_simple(0, 0, 0, 1),
+                Color {
+                    red: 0.0,
+                    green: 0.0,
+                    blue: 1.0,
+                    alpha: 1.0,
+                },
+            );
+
+        let actual_presentations = find_color_presentations(request, CancellationToken::none());
+        assert!(actual_presentations.is_empty());
+    }
+
+    #[test]
+    fn test_color_let() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\definecolor{foo}{rgb}{1,0.5,0}\n\\colorlet{bar}{foo}\n\\colorlet{baz}{bar!50!blue}\n\\colorlet{qux}{red!50}",
+            )])
+            .main("main.tex")
+            .build()
+            .document_color();
+
+        let actual_colors = find_document_colors(request, CancellationToken::none());
+        let actual_ranges: Vec<_> = actual_colors.iter().map(|color| color.range).collect();
+        assert_eq!(
+            actual_ranges,
+            vec![
+                Range::new_simple(0, 23, 0, 30),
+                Range::new_simple(1, 15, 1, 18),
+                Range::new_simple(2, 15, 2, 27),
+                Range::new_simple(3, 15, 3, 21),
+            ]
+        );
+
+        let actual_colors: Vec<_> = actual_colors
+            .into_iter()
+            .map(|info| (info.color.red, info.color.green, info.color.blue))
+            .collect();
+        assert_eq!(
+            actual_colors,
+            vec![
+                (1.0, 0.5, 0.0),
+                (1.0, 0.5, 0.0),
+                (0.5, 0.25, 0.5),
+                (1.0, 0.5, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_color_let_other_document() {
+        let request = FeatureTester::builder()
+            .files(vec![
+                ("main.tex", "\\include{colors}\n\\colorlet{bar}{foo}"),
+                ("colors.tex", r#"\definecolor{foo}{HTML}{FF8000}"#),
+            ])
+            .main("main.tex")
+            .build()
+            .document_color();
+
+        let actual_colors = find_document_colors(request, CancellationToken::none());
+        assert_eq!(actual_colors.len(), 1);
+        assert_eq!(actual_colors[0].range, Range::new_simple(1, 15, 1, 18));
+        assert_eq!(actual_colors[0].color.red, 1.0);
+    }
+
+    #[test]
+    fn test_color_let_unsupported() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\colorlet{foo}{unknown}\n\\colorlet{bar}{-red}\n\\colorlet{baz}{red!200!blue}",
+            )])
+            .main("main.tex")
+            .build()
+            .document_color();
+
+        let actual_colors = find_document_colors(request, CancellationToken::none());
+        assert!(actual_colors.is_empty());
+    }
+}
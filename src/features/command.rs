@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Result};
+use cancellation::CancellationToken;
+use cstree::TextRange;
+use lsp_types::{TextDocumentIdentifier, TextDocumentPositionParams, TextEdit, WorkspaceEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    syntax::{
+        latex::{self, HasCurly},
+        CstNode,
+    },
+    DocumentLanguage, LineIndexExt, Workspace,
+};
+
+use super::{build::find_build_document, build_dependency_graph, FeatureRequest};
+
+pub const BUILD_COMMAND: &str = "texlab.build";
+
+pub const FORWARD_SEARCH_COMMAND: &str = "texlab.forwardSearch";
+
+pub const CLEAN_AUXILIARY_COMMAND: &str = "texlab.cleanAuxiliary";
+
+pub const CHANGE_ENVIRONMENT_COMMAND: &str = "texlab.changeEnvironment";
+
+pub const SHOW_DEPENDENCY_GRAPH_COMMAND: &str = "texlab.showDependencyGraph";
+
+pub const COMMANDS: &[&str] = &[
+    BUILD_COMMAND,
+    FORWARD_SEARCH_COMMAND,
+    CLEAN_AUXILIARY_COMMAND,
+    CHANGE_ENVIRONMENT_COMMAND,
+    SHOW_DEPENDENCY_GRAPH_COMMAND,
+];
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEnvironmentParams {
+    #[serde(flatten)]
+    pub text_document_position: TextDocumentPositionParams,
+    pub new_name: String,
+}
+
+pub fn change_environment(
+    request: FeatureRequest<ChangeEnvironmentParams>,
+    _token: &CancellationToken,
+) -> Option<WorkspaceEdit> {
+    let main_document = request.main_document();
+    let data = main_document.data.as_latex()?;
+    let offset = main_document
+        .line_index
+        .offset_lsp(request.params.text_document_position.position);
+
+    let environment = data
+        .root
+        .descendants()
+        .filter_map(latex::Environment::cast)
+        .filter(|environment| environment.small_range().contains_inclusive(offset))
+        .last()?;
+
+    let mut edits = Vec::new();
+    for name in environment
+        .begin()
+        .and_then(|begin| begin.name())
+        .into_iter()
+        .chain(environment.end().and_then(|end| end.name()))
+    {
+        let range = match name.key() {
+            Some(key) => key.small_range(),
+            None => TextRange::empty(name.left_curly()?.text_range().end()),
+        };
+
+        edits.push(TextEdit::new(
+            main_document.line_index.line_col_lsp_range(range),
+            request.params.new_name.clone(),
+        ));
+    }
+
+    let mut changes = HashMap::new();
+    changes.insert(main_document.uri.as_ref().clone().into(), edits);
+    Some(WorkspaceEdit::new(changes))
+}
+
+pub fn clean_auxiliary(
+    request: FeatureRequest<TextDocumentIdentifier>,
+    _token: &CancellationToken,
+) -> Result<()> {
+    let document = find_build_document(&request);
+    if document.language() != DocumentLanguage::Latex || document.uri.scheme() != "file" {
+        return Ok(());
+    }
+
+    let path = document.uri.to_file_path().unwrap();
+    let options = request.context.options_for(&document.uri);
+    let current_dir = options
+        .root_directory
+        .as_ref()
+        .map(AsRef::as_ref)
+        .or_else(|| path.parent())
+        .unwrap();
+
+    let mut args = vec!["-c".to_string()];
+    if let Some(aux_directory) = &options.aux_directory {
+        args.push(format!("-outdir={}", aux_directory.to_string_lossy()));
+    }
+    args.push(path.to_string_lossy().into_owned());
+
+    let status = Command::new("latexmk")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .current_dir(current_dir)
+        .status()?;
+
+    if !status.success() {
+        bail!("latexmk exited with {}", status);
+    }
+
+    Ok(())
+}
+
+pub fn show_dependency_graph(workspace: &dyn Workspace) -> String {
+    build_dependency_graph(workspace, None).to_dot()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use lsp_types::{Range, Url};
+
+    use crate::{features::testing::FeatureTester, RangeExt};
+
+    use super::*;
+
+    #[test]
+    fn test_change_environment() {
+        let tester = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                indoc! {r#"
+                    \begin{foo}
+                        \begin{bar}
+                        \end{bar}
+                    \end{foo}
+                "#},
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(6)
+            .new_name("baz")
+            .build();
+
+        let uri: Url = tester.uri("main.tex").as_ref().clone().into();
+        let edit =
+            change_environment(tester.change_environment(), CancellationToken::none()).unwrap();
+
+        assert_eq!(
+            edit.changes.unwrap()[&uri],
+            vec![
+                TextEdit::new(Range::new_simple(1, 11, 1, 14), "baz".into()),
+                TextEdit::new(Range::new_simple(2, 9, 2, 12), "baz".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_change_environment_outside() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\begin{foo}\end{foo} bar"#)])
+            .main("main.tex")
+            .line(0)
+            .character(22)
+            .new_name("baz")
+            .build()
+            .change_environment();
+
+        let edit = change_environment(request, CancellationToken::none());
+        assert_eq!(edit, None);
+    }
+}
@@ -82,10 +82,7 @@ pub fn complete_includes<'a>(
 fn current_dir(context: &CursorContext<CompletionParams>, path_text: &str) -> Option<PathBuf> {
     let mut path = context
         .request
-        .context
-        .options
-        .read()
-        .unwrap()
+        .options()
         .root_directory
         .as_ref()
         .map(|root_directory| {
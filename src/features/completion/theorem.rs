@@ -18,7 +18,7 @@ pub fn complete_theorem_environments<'a>(
 
     for document in &context.request.subset.documents {
         if let Some(data) = document.data.as_latex() {
-            for environment in &data.extras.theorem_environments {
+            for environment in data.extras.theorem_environments.iter() {
                 items.push(InternalCompletionItem::new(
                     range,
                     InternalCompletionItemData::UserEnvironment {
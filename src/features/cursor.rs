@@ -1,7 +1,7 @@
 use cstree::{TextRange, TextSize};
 use lsp_types::{
     CompletionParams, DocumentHighlightParams, GotoDefinitionParams, HoverParams, Position,
-    ReferenceParams, RenameParams, TextDocumentPositionParams,
+    ReferenceParams, RenameParams, SignatureHelpParams, TextDocumentPositionParams,
 };
 
 use crate::{
@@ -224,6 +224,15 @@ impl<P: HasPosition> CursorContext<P> {
         }
     }
 
+    pub fn find_command_name(&self) -> Option<(String, TextRange)> {
+        let token = self
+            .cursor
+            .as_latex()
+            .filter(|token| token.kind() == latex::GENERIC_COMMAND_NAME)?;
+
+        Some((token.text().to_string(), token.text_range()))
+    }
+
     pub fn find_environment_name(&self) -> Option<(String, TextRange)> {
         let (name, range, group) = self.find_curly_group_word()?;
 
@@ -267,6 +276,49 @@ impl<P: HasPosition> CursorContext<P> {
         key.map(|key| (key.to_string(), key.small_range(), group))
             .or_else(|| Some((String::new(), TextRange::empty(self.offset), group)))
     }
+
+    pub fn find_command_argument(&self) -> Option<(String, usize)> {
+        let group = self.find_argument_group(latex::GENERIC_COMMAND)?;
+        let command = latex::GenericCommand::cast(group.syntax().parent()?)?;
+        let index = command
+            .syntax()
+            .children()
+            .filter_map(latex::CurlyGroup::cast)
+            .position(|g| g.syntax().text_range() == group.syntax().text_range())?;
+
+        Some((command.name()?.text().to_string(), index))
+    }
+
+    pub fn find_environment_argument(&self) -> Option<(String, usize)> {
+        let group = self.find_argument_group(latex::ENVIRONMENT)?;
+        let environment = latex::Environment::cast(group.syntax().parent()?)?;
+        let index = environment
+            .syntax()
+            .children()
+            .skip_while(|node| node.kind() == latex::BEGIN)
+            .take_while(|node| node.kind() == latex::CURLY_GROUP)
+            .filter_map(latex::CurlyGroup::cast)
+            .position(|g| g.syntax().text_range() == group.syntax().text_range())?;
+
+        let name = environment.begin()?.name()?.key()?;
+        Some((name.to_string(), index))
+    }
+
+    fn find_argument_group(&self, parent_kind: latex::SyntaxKind) -> Option<latex::CurlyGroup> {
+        self.cursor
+            .as_latex()?
+            .parent()
+            .ancestors()
+            .filter_map(latex::CurlyGroup::cast)
+            .filter(|group| group.syntax().text_range().start() < self.offset)
+            .filter(|group| self.is_inside_latex_curly(group))
+            .find(|group| {
+                group
+                    .syntax()
+                    .parent()
+                    .map_or(false, |parent| parent.kind() == parent_kind)
+            })
+    }
 }
 
 pub trait HasPosition {
@@ -314,3 +366,9 @@ impl HasPosition for DocumentHighlightParams {
         self.text_document_position_params.position
     }
 }
+
+impl HasPosition for SignatureHelpParams {
+    fn position(&self) -> Position {
+        self.text_document_position_params.position
+    }
+}
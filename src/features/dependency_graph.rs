@@ -0,0 +1,433 @@
+use std::{fmt::Write, path::Path, str::FromStr, sync::Arc};
+
+use anyhow::{bail, Result};
+use lsp_types::{TextDocumentIdentifier, Url};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    component_db::COMPONENT_DATABASE,
+    syntax::{
+        latex::{self, ExplicitLink, ExplicitLinkKind},
+        CstNode,
+    },
+    Document, Uri, Workspace,
+};
+
+const GRAPHICS_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "jpeg", "eps", "svg"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Tex,
+    Bib,
+    Sty,
+    Cls,
+    Graphics,
+}
+
+impl DependencyKind {
+    fn by_uri(uri: &Uri) -> Option<Self> {
+        let extension = Path::new(uri.path()).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "tex" => Some(Self::Tex),
+            "bib" => Some(Self::Bib),
+            "sty" => Some(Self::Sty),
+            "cls" => Some(Self::Cls),
+            extension if GRAPHICS_EXTENSIONS.contains(&extension) => Some(Self::Graphics),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Tex => "tex",
+            Self::Bib => "bib",
+            Self::Sty => "sty",
+            Self::Cls => "cls",
+            Self::Graphics => "graphics",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyNode {
+    pub uri: Url,
+    pub kind: DependencyKind,
+    /// Set if the target of an include does not exist.
+    pub missing: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyEdge {
+    pub source: usize,
+    pub target: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyGraph {
+    pub nodes: Vec<DependencyNode>,
+    pub edges: Vec<DependencyEdge>,
+}
+
+impl DependencyGraph {
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph G {\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            write!(
+                dot,
+                "\tv{:0>4} [label=\"{}\", kind=\"{}\", shape={}",
+                i,
+                node.uri,
+                node.kind.as_str(),
+                match node.kind {
+                    DependencyKind::Tex => "box",
+                    DependencyKind::Bib => "cylinder",
+                    DependencyKind::Sty | DependencyKind::Cls => "component",
+                    DependencyKind::Graphics => "note",
+                }
+            )
+            .unwrap();
+
+            if node.missing {
+                dot.push_str(", style=dashed, color=red");
+            }
+
+            dot.push_str("];\n");
+        }
+
+        for edge in &self.edges {
+            writeln!(dot, "\tv{:0>4} -> v{:0>4};", edge.source, edge.target).unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The output format of the `graph` subcommand.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "dot" => Ok(Self::Dot),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown output format: {}", value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyGraphParams {
+    /// Restricts the graph to the project of the given document.
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+#[derive(Default)]
+struct GraphBuilder {
+    graph: DependencyGraph,
+    indices: FxHashMap<Uri, usize>,
+}
+
+impl GraphBuilder {
+    fn add_node(&mut self, uri: &Uri, kind: DependencyKind, missing: bool) -> usize {
+        if let Some(index) = self.indices.get(uri) {
+            return *index;
+        }
+
+        let index = self.graph.nodes.len();
+        self.graph.nodes.push(DependencyNode {
+            uri: uri.clone().into(),
+            kind,
+            missing,
+        });
+        self.indices.insert(uri.clone(), index);
+        index
+    }
+
+    fn add_edge(&mut self, source: usize, target: usize) {
+        let edge = DependencyEdge { source, target };
+        if !self.graph.edges.contains(&edge) {
+            self.graph.edges.push(edge);
+        }
+    }
+}
+
+/// Builds the include graph of the workspace
+/// or of the project containing the given document.
+/// Only the includes of `.tex` files are followed, so packages and classes are leaves of the graph.
+pub fn build_dependency_graph(workspace: &dyn Workspace, uri: Option<Arc<Uri>>) -> DependencyGraph {
+    let documents = match uri {
+        Some(uri) => workspace
+            .subset(uri)
+            .map(|subset| subset.documents)
+            .unwrap_or_default(),
+        None => workspace.documents(),
+    };
+
+    let sources: Vec<_> = documents
+        .iter()
+        .filter(|document| DependencyKind::by_uri(&document.uri) == Some(DependencyKind::Tex))
+        .collect();
+
+    let mut builder = GraphBuilder::default();
+    for document in &sources {
+        builder.add_node(&document.uri, DependencyKind::Tex, false);
+    }
+
+    for document in sources {
+        let source = builder.indices[document.uri.as_ref()];
+        let data = match document.data.as_latex() {
+            Some(data) => data,
+            None => continue,
+        };
+
+        for link in &data.extras.explicit_links {
+            let kind = match link.kind {
+                ExplicitLinkKind::Latex => DependencyKind::Tex,
+                ExplicitLinkKind::Bibtex => DependencyKind::Bib,
+                ExplicitLinkKind::Package => DependencyKind::Sty,
+                ExplicitLinkKind::Class => DependencyKind::Cls,
+            };
+
+            // Existing targets are loaded into the workspace by the children expander,
+            // except for the packages and classes that are known from the component database.
+            let target = match resolve_link(&documents, link) {
+                Some(index) => builder.add_node(&documents[index].uri, kind, false),
+                None => {
+                    let missing = link
+                        .as_component_name()
+                        .and_then(|name| COMPONENT_DATABASE.find(&name))
+                        .is_none();
+
+                    match link
+                        .targets
+                        .iter()
+                        .find(|target| DependencyKind::by_uri(target) == Some(kind))
+                        .or_else(|| link.targets.first())
+                    {
+                        Some(target) => builder.add_node(target, kind, missing),
+                        None => continue,
+                    }
+                }
+            };
+
+            builder.add_edge(source, target);
+        }
+
+        for (target, missing) in find_graphics(workspace, document) {
+            let target = builder.add_node(&target, DependencyKind::Graphics, missing);
+            builder.add_edge(source, target);
+        }
+    }
+
+    builder.graph
+}
+
+/// Returns the index of the document that the link refers to.
+/// The first target that is part of the given documents wins.
+pub(crate) fn resolve_link(documents: &[Arc<Document>], link: &ExplicitLink) -> Option<usize> {
+    link.targets.iter().find_map(|target| {
+        documents
+            .iter()
+            .position(|document| document.uri.as_ref() == target.as_ref())
+    })
+}
+
+/// Finds the images included by a document and whether they are missing.
+fn find_graphics(workspace: &dyn Workspace, document: &Document) -> Vec<(Uri, bool)> {
+    let data = match document.data.as_latex() {
+        Some(data) => data,
+        None => return Vec::new(),
+    };
+
+    let base_uri = workspace
+        .context()
+        .options_for(&document.uri)
+        .root_directory
+        .and_then(|root_dir| Uri::from_directory_path(root_dir).ok())
+        .unwrap_or_else(|| document.uri.as_ref().clone());
+
+    let mut graphics = Vec::new();
+    for include in data.root.descendants().filter_map(latex::Include::cast) {
+        match include.syntax().kind() {
+            latex::GRAPHICS_INCLUDE | latex::SVG_INCLUDE | latex::INKSCAPE_INCLUDE => {}
+            _ => continue,
+        }
+
+        let paths = match include.path_list() {
+            Some(paths) => paths,
+            None => continue,
+        };
+
+        for path in paths.keys() {
+            let stem = path.to_string();
+            let candidates: Vec<Uri> = std::iter::once(stem.clone())
+                .chain(
+                    GRAPHICS_EXTENSIONS
+                        .iter()
+                        .map(|extension| format!("{}.{}", stem, extension)),
+                )
+                .filter_map(|path| base_uri.join(&path).ok().map(Into::into))
+                .collect();
+
+            match candidates.iter().find(|candidate| exists(candidate)) {
+                Some(candidate) => graphics.push((candidate.clone(), false)),
+                None => {
+                    if let Some(candidate) = candidates.into_iter().next() {
+                        graphics.push((candidate, true));
+                    }
+                }
+            }
+        }
+    }
+
+    graphics
+}
+
+fn exists(uri: &Uri) -> bool {
+    uri.scheme() == "file" && uri.to_file_path().map_or(false, |path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::{create_workspace_fast, DocumentLanguage, ServerContext, WorkspaceSource};
+
+    use super::*;
+
+    fn find_node<'a>(graph: &'a DependencyGraph, name: &str) -> (usize, &'a DependencyNode) {
+        graph
+            .nodes
+            .iter()
+            .enumerate()
+            .find(|(_, node)| node.uri.path().ends_with(name))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let directory = tempdir().unwrap();
+        fs::write(directory.path().join("figure.png"), "").unwrap();
+
+        let workspace: Arc<dyn Workspace> = Arc::new(
+            create_workspace_fast(Arc::new(ServerContext::new(directory.path().to_path_buf())))
+                .unwrap(),
+        );
+
+        let main_uri = Uri::from_file_path(directory.path().join("main.tex")).unwrap();
+        workspace.open(
+            Arc::new(main_uri),
+            r#"\input{chapter}\bibliography{refs}\includegraphics{figure}\includegraphics{missing}"#
+                .into(),
+            DocumentLanguage::Latex,
+            WorkspaceSource::Client,
+        );
+
+        let chapter_uri = Uri::from_file_path(directory.path().join("chapter.tex")).unwrap();
+        workspace.open(
+            Arc::new(chapter_uri),
+            String::new(),
+            DocumentLanguage::Latex,
+            WorkspaceSource::Client,
+        );
+
+        let graph = build_dependency_graph(workspace.as_ref(), None);
+        let (main, _) = find_node(&graph, "/main.tex");
+        let (chapter, chapter_node) = find_node(&graph, "/chapter.tex");
+        let (refs, refs_node) = find_node(&graph, "/refs.bib");
+        let (figure, figure_node) = find_node(&graph, "/figure.png");
+        let (missing, missing_node) = find_node(&graph, "/missing");
+
+        assert_eq!(graph.nodes.len(), 5);
+        assert!(!chapter_node.missing);
+        assert_eq!(refs_node.kind, DependencyKind::Bib);
+        assert!(refs_node.missing);
+        assert_eq!(figure_node.kind, DependencyKind::Graphics);
+        assert!(!figure_node.missing);
+        assert_eq!(missing_node.kind, DependencyKind::Graphics);
+        assert!(missing_node.missing);
+
+        let mut edges = graph.edges.clone();
+        edges.sort_by_key(|edge| edge.target);
+        let mut expected_edges: Vec<_> = [chapter, refs, figure, missing]
+            .iter()
+            .map(|target| DependencyEdge {
+                source: main,
+                target: *target,
+            })
+            .collect();
+        expected_edges.sort_by_key(|edge| edge.target);
+        assert_eq!(edges, expected_edges);
+    }
+
+    #[test]
+    fn test_dependency_graph_component() {
+        let directory = tempdir().unwrap();
+        let workspace: Arc<dyn Workspace> = Arc::new(
+            create_workspace_fast(Arc::new(ServerContext::new(directory.path().to_path_buf())))
+                .unwrap(),
+        );
+
+        let main_uri = Uri::from_file_path(directory.path().join("main.tex")).unwrap();
+        workspace.open(
+            Arc::new(main_uri),
+            r#"\usepackage{amsmath}\usepackage{foo-bar-baz}"#.into(),
+            DocumentLanguage::Latex,
+            WorkspaceSource::Client,
+        );
+
+        let graph = build_dependency_graph(workspace.as_ref(), None);
+        let (_, amsmath_node) = find_node(&graph, "/amsmath.sty");
+        let (_, unknown_node) = find_node(&graph, "/foo-bar-baz.sty");
+
+        assert_eq!(amsmath_node.kind, DependencyKind::Sty);
+        assert!(!amsmath_node.missing);
+        assert!(unknown_node.missing);
+    }
+
+    #[test]
+    fn test_dependency_graph_dot() {
+        let graph = DependencyGraph {
+            nodes: vec![
+                DependencyNode {
+                    uri: Url::parse("file:///main.tex").unwrap(),
+                    kind: DependencyKind::Tex,
+                    missing: false,
+                },
+                DependencyNode {
+                    uri: Url::parse("file:///refs.bib").unwrap(),
+                    kind: DependencyKind::Bib,
+                    missing: true,
+                },
+            ],
+            edges: vec![DependencyEdge {
+                source: 0,
+                target: 1,
+            }],
+        };
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph G {\n\
+             \tv0000 [label=\"file:///main.tex\", kind=\"tex\", shape=box];\n\
+             \tv0001 [label=\"file:///refs.bib\", kind=\"bib\", shape=cylinder, style=dashed, color=red];\n\
+             \tv0000 -> v0001;\n\
+             }\n"
+        );
+    }
+}
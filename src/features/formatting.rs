@@ -2,11 +2,17 @@ mod bibtex_internal;
 mod latexindent;
 
 use cancellation::CancellationToken;
-use lsp_types::{DocumentFormattingParams, TextEdit};
+use lsp_types::{
+    DocumentFormattingParams, DocumentOnTypeFormattingParams, DocumentRangeFormattingParams,
+    TextEdit,
+};
 
 use crate::{BibtexFormatter, LatexFormatter};
 
-use self::{bibtex_internal::format_bibtex_internal, latexindent::format_with_latexindent};
+use self::{
+    bibtex_internal::{format_bibtex_internal, format_bibtex_on_type, format_bibtex_range},
+    latexindent::format_with_latexindent,
+};
 
 use super::FeatureRequest;
 
@@ -15,14 +21,36 @@ pub fn format_source_code(
     cancellation_token: &CancellationToken,
 ) -> Option<Vec<TextEdit>> {
     let mut edits = None;
-    if request.context.options.read().unwrap().bibtex_formatter == BibtexFormatter::Texlab {
+    if request.options().bibtex_formatter == BibtexFormatter::Texlab {
         edits = edits.or_else(|| format_bibtex_internal(&request, cancellation_token));
     }
 
-    if request.context.options.read().unwrap().latex_formatter == LatexFormatter::Texlab {
+    if request.options().latex_formatter == LatexFormatter::Texlab {
         edits = edits.or_else(|| Some(vec![]));
     }
 
     edits = edits.or_else(|| format_with_latexindent(&request, cancellation_token));
     edits
 }
+
+pub fn format_source_code_range(
+    request: FeatureRequest<DocumentRangeFormattingParams>,
+    cancellation_token: &CancellationToken,
+) -> Option<Vec<TextEdit>> {
+    if request.options().bibtex_formatter == BibtexFormatter::Texlab {
+        format_bibtex_range(&request, cancellation_token)
+    } else {
+        None
+    }
+}
+
+pub fn format_source_code_on_type(
+    request: FeatureRequest<DocumentOnTypeFormattingParams>,
+    cancellation_token: &CancellationToken,
+) -> Option<Vec<TextEdit>> {
+    if request.options().bibtex_formatter == BibtexFormatter::Texlab {
+        format_bibtex_on_type(&request, cancellation_token)
+    } else {
+        None
+    }
+}
@@ -1,6 +1,9 @@
 use cancellation::CancellationToken;
-use cstree::NodeOrToken;
-use lsp_types::{DocumentFormattingParams, TextEdit};
+use cstree::{NodeOrToken, TextRange};
+use lsp_types::{
+    DocumentFormattingParams, DocumentOnTypeFormattingParams, DocumentRangeFormattingParams,
+    FormattingOptions, TextEdit,
+};
 
 use crate::{
     features::FeatureRequest,
@@ -14,32 +17,78 @@ use crate::{
 pub fn format_bibtex_internal(
     request: &FeatureRequest<DocumentFormattingParams>,
     _cancellation_token: &CancellationToken,
+) -> Option<Vec<TextEdit>> {
+    format_nodes(request, &request.params.options, |_, _| true)
+}
+
+pub fn format_bibtex_range(
+    request: &FeatureRequest<DocumentRangeFormattingParams>,
+    _cancellation_token: &CancellationToken,
+) -> Option<Vec<TextEdit>> {
+    let range = request
+        .main_document()
+        .line_index
+        .offset_lsp_range(request.params.range);
+
+    format_nodes(request, &request.params.options, |_, node_range| {
+        if range.is_empty() {
+            node_range.contains_inclusive(range.start())
+        } else {
+            node_range
+                .intersect(range)
+                .map_or(false, |intersection| !intersection.is_empty())
+        }
+    })
+}
+
+pub fn format_bibtex_on_type(
+    request: &FeatureRequest<DocumentOnTypeFormattingParams>,
+    _cancellation_token: &CancellationToken,
+) -> Option<Vec<TextEdit>> {
+    let offset = request
+        .main_document()
+        .line_index
+        .offset_lsp(request.params.text_document_position.position);
+
+    // Typing usually happens inside of incomplete entries,
+    // which would be mangled by the formatter.
+    format_nodes(request, &request.params.options, |node, node_range| {
+        node_range.contains_inclusive(offset) && is_complete(node)
+    })
+}
+
+/// Checks if a node has all of its delimiters and no syntax errors.
+fn is_complete(node: &bibtex::SyntaxNode) -> bool {
+    !node
+        .descendants_with_tokens()
+        .any(|element| matches!(element.kind(), bibtex::ERROR | bibtex::MISSING))
+}
+
+fn format_nodes<P>(
+    request: &FeatureRequest<P>,
+    options: &FormattingOptions,
+    filter: impl Fn(&bibtex::SyntaxNode, TextRange) -> bool,
 ) -> Option<Vec<TextEdit>> {
     let mut indent = String::new();
-    if request.params.options.insert_spaces {
-        for _ in 0..request.params.options.tab_size {
+    if options.insert_spaces {
+        for _ in 0..options.tab_size {
             indent.push(' ');
         }
     } else {
         indent.push('\t');
     }
 
-    let line_length = {
-        request
-            .context
-            .options
-            .read()
-            .unwrap()
-            .formatter_line_length
-            .map(|value| {
-                if value <= 0 {
-                    usize::MAX
-                } else {
-                    value as usize
-                }
-            })
-            .unwrap_or(80)
-    };
+    let line_length = request
+        .options()
+        .formatter_line_length
+        .map(|value| {
+            if value <= 0 {
+                usize::MAX
+            } else {
+                value as usize
+            }
+        })
+        .unwrap_or(80);
 
     let document = request.main_document();
     let data = document.data.as_bibtex()?;
@@ -56,9 +105,13 @@ pub fn format_bibtex_internal(
             continue;
         };
 
+        if !filter(node, range) {
+            continue;
+        }
+
         let mut formatter = Formatter::new(
             indent.clone(),
-            request.params.options.tab_size,
+            options.tab_size,
             line_length,
             &document.line_index,
         );
@@ -225,8 +278,9 @@ impl<'a> Formatter<'a> {
 #[cfg(test)]
 mod tests {
     use insta::{assert_debug_snapshot, assert_display_snapshot};
+    use lsp_types::Range;
 
-    use crate::features::testing::FeatureTester;
+    use crate::{features::testing::FeatureTester, RangeExt};
 
     use super::*;
 
@@ -378,4 +432,79 @@ mod tests {
 
         assert_display_snapshot!(edit.new_text);
     }
+
+    #[test]
+    fn test_range() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.bib",
+                "@article{foo, bar = baz}\n@article{qux, bar = baz}\n@article{quux, bar = baz}",
+            )])
+            .main("main.bib")
+            .line(1)
+            .character(3)
+            .build()
+            .range_formatting();
+
+        let edits = format_bibtex_range(&request, CancellationToken::none()).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, Range::new_simple(1, 0, 1, 24));
+        assert_eq!(edits[0].new_text, "@article{qux,\n\tbar = baz,\n}");
+    }
+
+    #[test]
+    fn test_on_type() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.bib",
+                "@article{foo, bar = baz}\n@article{qux, bar = baz,}",
+            )])
+            .main("main.bib")
+            .line(1)
+            .character(25)
+            .build()
+            .on_type_formatting();
+
+        let edits = format_bibtex_on_type(&request, CancellationToken::none()).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, Range::new_simple(1, 0, 1, 25));
+        assert_eq!(edits[0].new_text, "@article{qux,\n\tbar = baz,\n}");
+    }
+
+    #[test]
+    fn test_range_multiple_entries() {
+        let mut request = FeatureTester::builder()
+            .files(vec![(
+                "main.bib",
+                "@article{foo, bar = baz}\n@article{qux, bar = baz}\n@article{quux, bar = baz}",
+            )])
+            .main("main.bib")
+            .build()
+            .range_formatting();
+
+        request.params.range = Range::new_simple(0, 10, 1, 5);
+        let edits = format_bibtex_range(&request, CancellationToken::none()).unwrap();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range, Range::new_simple(0, 0, 0, 24));
+        assert_eq!(edits[0].new_text, "@article{foo,\n\tbar = baz,\n}");
+        assert_eq!(edits[1].range, Range::new_simple(1, 0, 1, 24));
+        assert_eq!(edits[1].new_text, "@article{qux,\n\tbar = baz,\n}");
+    }
+
+    #[test]
+    fn test_on_type_incomplete_entry() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.bib",
+                "@article{foo, bar = baz}\n@article{qux, bar = {baz}",
+            )])
+            .main("main.bib")
+            .line(1)
+            .character(25)
+            .build()
+            .on_type_formatting();
+
+        let edits = format_bibtex_on_type(&request, CancellationToken::none()).unwrap();
+        assert!(edits.is_empty());
+    }
 }
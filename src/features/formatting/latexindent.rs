@@ -5,10 +5,11 @@ use std::{
 
 use cancellation::CancellationToken;
 use cstree::{TextLen, TextRange};
+use log::warn;
 use lsp_types::{DocumentFormattingParams, TextEdit};
 use tempfile::tempdir;
 
-use crate::{features::FeatureRequest, DocumentLanguage, LineIndexExt};
+use crate::{encoding, features::FeatureRequest, DocumentLanguage, LineIndexExt};
 
 pub fn format_with_latexindent(
     request: &FeatureRequest<DocumentFormattingParams>,
@@ -17,7 +18,7 @@ pub fn format_with_latexindent(
     let directory = tempdir().ok()?;
     let document = request.main_document();
 
-    let options = request.context.options.read().unwrap();
+    let options = request.options();
     let current_dir = options
         .root_directory
         .as_ref()
@@ -43,8 +44,6 @@ pub fn format_with_latexindent(
 
     let modify_line_breaks = options.latexindent.modify_line_breaks;
 
-    drop(options);
-
     let path = directory.path();
     let _ = fs::copy(
         current_dir.join("localSettings.yaml"),
@@ -65,7 +64,19 @@ pub fn format_with_latexindent(
         "file.tex"
     };
 
-    fs::write(directory.path().join(name), &document.text).ok()?;
+    // latexindent needs to see the document in its original encoding.
+    let encoding = encoding::detect_encoding(&document.text, options.default_encoding.as_deref());
+    let (data, _, had_unmappable_characters) = encoding.encode(&document.text);
+    if had_unmappable_characters {
+        warn!(
+            "Unable to format {}: the text cannot be encoded in {}",
+            document.uri,
+            encoding.name()
+        );
+        return None;
+    }
+
+    fs::write(directory.path().join(name), data).ok()?;
 
     let mut args = Vec::new();
     if modify_line_breaks {
@@ -84,7 +95,8 @@ pub fn format_with_latexindent(
         .output()
         .ok()?;
 
-    let new_text = String::from_utf8_lossy(&output.stdout).into_owned();
+    let (new_text, _) = encoding.decode_with_bom_removal(&output.stdout);
+    let new_text = new_text.into_owned();
     if new_text.is_empty() {
         None
     } else {
@@ -30,16 +30,7 @@ pub fn execute_forward_search(
     request: FeatureRequest<TextDocumentPositionParams>,
     _cancellation_token: &CancellationToken,
 ) -> Option<ForwardSearchResult> {
-    let options = {
-        request
-            .context
-            .options
-            .read()
-            .unwrap()
-            .forward_search
-            .clone()
-            .unwrap_or_default()
-    };
+    let options = request.options().forward_search.unwrap_or_default();
 
     if options.executable.is_none() || options.args.is_none() {
         return Some(ForwardSearchResult {
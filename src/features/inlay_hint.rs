@@ -0,0 +1,325 @@
+use cancellation::CancellationToken;
+use cstree::{TextRange, TextSize};
+use lsp_types::{Position, Range, TextDocumentIdentifier};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    find_label_number, render_label,
+    syntax::{latex, CstNode},
+    Document, LabelledObject, LineIndexExt, WorkspaceSubset,
+};
+
+use super::FeatureRequest;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHintParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHint {
+    pub position: Position,
+    pub label: String,
+    pub padding_left: bool,
+}
+
+pub fn find_inlay_hints(
+    request: FeatureRequest<InlayHintParams>,
+    token: &CancellationToken,
+) -> Vec<InlayHint> {
+    let mut hints = Vec::new();
+    let main_document = request.main_document();
+    let data = match main_document.data.as_latex() {
+        Some(data) => data,
+        None => return hints,
+    };
+
+    let range = clamp_range(main_document, request.params.range);
+
+    for node in data.root.descendants() {
+        if token.is_canceled() {
+            break;
+        }
+
+        if range.intersect(node.text_range()).is_none() {
+            continue;
+        }
+
+        if let Some(reference) = latex::LabelReference::cast(node) {
+            for key in reference
+                .name_list()
+                .into_iter()
+                .flat_map(|group| group.keys())
+            {
+                let label = render_reference(&request.subset, &key.to_string());
+                add_hint(&mut hints, main_document, key, label);
+            }
+        } else if let Some(reference) = latex::LabelReferenceRange::cast(node) {
+            for key in reference
+                .from()
+                .into_iter()
+                .chain(reference.to())
+                .filter_map(|group| group.key())
+            {
+                let label = render_reference(&request.subset, &key.to_string());
+                add_hint(&mut hints, main_document, key, label);
+            }
+        } else if let Some(citation) = latex::Citation::cast(node) {
+            for key in citation
+                .key_list()
+                .into_iter()
+                .flat_map(|group| group.keys())
+            {
+                let label = render_citation(&request.subset, &key.to_string());
+                add_hint(&mut hints, main_document, key, label);
+            }
+        }
+    }
+
+    hints
+}
+
+/// Converts the range of the request while clamping it to the bounds of the document
+/// because the client may send positions after the last line.
+fn clamp_range(document: &Document, range: Range) -> TextRange {
+    let end_of_document = TextSize::of(document.text.as_str());
+    let clamp = |position: Position| {
+        if position.line as usize >= document.line_index.line_count() {
+            end_of_document
+        } else {
+            document
+                .line_index
+                .offset_lsp(position)
+                .min(end_of_document)
+        }
+    };
+
+    let start = clamp(range.start);
+    let end = clamp(range.end).max(start);
+    TextRange::new(start, end)
+}
+
+fn add_hint(
+    hints: &mut Vec<InlayHint>,
+    document: &Document,
+    key: latex::Key,
+    label: Option<String>,
+) {
+    if let Some(label) = label {
+        hints.push(InlayHint {
+            position: document.line_index.line_col_lsp(key.small_range().end()),
+            label,
+            padding_left: true,
+        });
+    }
+}
+
+fn render_reference(subset: &WorkspaceSubset, name: &str) -> Option<String> {
+    let number = find_label_number(subset, name)?;
+    let label = match render_label(subset, name, None).map(|label| label.object) {
+        Some(LabelledObject::Section { prefix, .. }) => format!("{} {}", prefix, number),
+        Some(LabelledObject::Float { kind, .. }) => format!("{} {}", kind.as_str(), number),
+        Some(LabelledObject::Theorem { kind, .. }) => format!("{} {}", kind, number),
+        Some(LabelledObject::Equation) => format!("({})", number),
+        Some(LabelledObject::EnumItem) | None => number.to_string(),
+    };
+
+    Some(label)
+}
+
+fn render_citation(subset: &WorkspaceSubset, key: &str) -> Option<String> {
+    subset.documents.iter().find_map(|document| {
+        document
+            .data
+            .as_latex()
+            .and_then(|data| data.extras.citation_labels_by_key.get(key))
+            .map(|label| format!("[{}]", label))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::features::testing::FeatureTester;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .build()
+            .inlay_hint();
+
+        let actual_hints = find_inlay_hints(request, CancellationToken::none());
+        assert!(actual_hints.is_empty());
+    }
+
+    #[test]
+    fn test_empty_bibtex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.bib", "")])
+            .main("main.bib")
+            .build()
+            .inlay_hint();
+
+        let actual_hints = find_inlay_hints(request, CancellationToken::none());
+        assert!(actual_hints.is_empty());
+    }
+
+    #[test]
+    fn test_label_references() {
+        let request = FeatureTester::builder()
+            .files(vec![
+                (
+                    "main.tex",
+                    indoc! {r#"
+                        \begin{figure}
+                            \caption{Foo}
+                            \label{fig:foo}
+                        \end{figure}
+                        \begin{equation}
+                            \label{eq:bar}
+                        \end{equation}
+                        \ref{fig:foo} \eqref{eq:bar} \ref{baz}
+                    "#},
+                ),
+                (
+                    "main.aux",
+                    indoc! {r#"
+                        \newlabel{fig:foo}{{3.2}{1}}
+                        \newlabel{eq:bar}{{4.1}{1}}
+                    "#},
+                ),
+            ])
+            .main("main.tex")
+            .build()
+            .inlay_hint();
+
+        let actual_hints = find_inlay_hints(request, CancellationToken::none());
+        assert_eq!(
+            actual_hints,
+            vec![
+                InlayHint {
+                    position: Position::new(7, 12),
+                    label: "Figure 3.2".into(),
+                    padding_left: true,
+                },
+                InlayHint {
+                    position: Position::new(7, 27),
+                    label: "(4.1)".into(),
+                    padding_left: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_citations() {
+        let request = FeatureTester::builder()
+            .files(vec![
+                ("main.tex", r#"\cite{foo,bar}"#),
+                ("main.aux", r#"\bibcite{foo}{12}"#),
+            ])
+            .main("main.tex")
+            .build()
+            .inlay_hint();
+
+        let actual_hints = find_inlay_hints(request, CancellationToken::none());
+        assert_eq!(
+            actual_hints,
+            vec![InlayHint {
+                position: Position::new(0, 9),
+                label: "[12]".into(),
+                padding_left: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_citations_bbl() {
+        let request = FeatureTester::builder()
+            .files(vec![
+                ("main.tex", r#"\cite{foo,bar,baz}"#),
+                (
+                    "main.bbl",
+                    indoc! {r#"
+                        \begin{thebibliography}{2}
+                        \bibitem{foo} Foo.
+                        \bibitem{bar} Bar.
+                        \end{thebibliography}
+                    "#},
+                ),
+            ])
+            .main("main.tex")
+            .build()
+            .inlay_hint();
+
+        let actual_hints = find_inlay_hints(request, CancellationToken::none());
+        assert_eq!(
+            actual_hints,
+            vec![
+                InlayHint {
+                    position: Position::new(0, 9),
+                    label: "[1]".into(),
+                    padding_left: true,
+                },
+                InlayHint {
+                    position: Position::new(0, 13),
+                    label: "[2]".into(),
+                    padding_left: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_citations_bbl_with_label() {
+        let request = FeatureTester::builder()
+            .files(vec![
+                ("main.tex", r#"\cite{foo}"#),
+                ("main.bbl", r#"\bibitem[Foo99]{foo} Foo."#),
+            ])
+            .main("main.tex")
+            .build()
+            .inlay_hint();
+
+        let actual_hints = find_inlay_hints(request, CancellationToken::none());
+        assert_eq!(
+            actual_hints,
+            vec![InlayHint {
+                position: Position::new(0, 9),
+                label: "[Foo99]".into(),
+                padding_left: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_range_after_last_line() {
+        let mut request = FeatureTester::builder()
+            .files(vec![
+                ("main.tex", "\\cite{foo}\n"),
+                ("main.aux", r#"\bibcite{foo}{12}"#),
+            ])
+            .main("main.tex")
+            .build()
+            .inlay_hint();
+
+        request.params.range = Range::new(Position::new(0, 0), Position::new(2, 5));
+        let actual_hints = find_inlay_hints(request, CancellationToken::none());
+        assert_eq!(
+            actual_hints,
+            vec![InlayHint {
+                position: Position::new(0, 9),
+                label: "[12]".into(),
+                padding_left: true,
+            }]
+        );
+    }
+}
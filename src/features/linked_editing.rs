@@ -0,0 +1,201 @@
+use cancellation::CancellationToken;
+use cstree::TextRange;
+use lsp_types::{LinkedEditingRangeParams, LinkedEditingRanges};
+
+use crate::{
+    syntax::{
+        latex::{self, HasCurly},
+        CstNode,
+    },
+    LineIndexExt,
+};
+
+use super::FeatureRequest;
+
+pub fn find_linked_editing_ranges(
+    request: FeatureRequest<LinkedEditingRangeParams>,
+    token: &CancellationToken,
+) -> Option<LinkedEditingRanges> {
+    let main_document = request.main_document();
+    let data = main_document.data.as_latex()?;
+    let offset = main_document
+        .line_index
+        .offset_lsp(request.params.text_document_position_params.position);
+
+    for environment in data.root.descendants().filter_map(latex::Environment::cast) {
+        if token.is_canceled() {
+            return None;
+        }
+
+        let begin_range = match environment
+            .begin()
+            .and_then(|begin| begin.name())
+            .and_then(find_name_range)
+        {
+            Some(range) => range,
+            None => continue,
+        };
+
+        let end_range = match environment
+            .end()
+            .and_then(|end| end.name())
+            .and_then(find_name_range)
+        {
+            Some(range) => range,
+            None => continue,
+        };
+
+        if !begin_range.contains_inclusive(offset) && !end_range.contains_inclusive(offset) {
+            continue;
+        }
+
+        if main_document.text[begin_range] != main_document.text[end_range] {
+            return None;
+        }
+
+        return Some(LinkedEditingRanges {
+            ranges: vec![
+                main_document.line_index.line_col_lsp_range(begin_range),
+                main_document.line_index.line_col_lsp_range(end_range),
+            ],
+            word_pattern: Some(r"[^\s\\{}%]*".into()),
+        });
+    }
+
+    None
+}
+
+fn find_name_range(name: latex::CurlyGroupWord) -> Option<TextRange> {
+    match name.key() {
+        Some(key) => Some(key.small_range()),
+        None => Some(TextRange::empty(name.left_curly()?.text_range().end())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Range;
+
+    use crate::{features::testing::FeatureTester, RangeExt};
+
+    use super::*;
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .line(0)
+            .character(0)
+            .build()
+            .linked_editing_range();
+
+        let actual_ranges = find_linked_editing_ranges(request, CancellationToken::none());
+        assert_eq!(actual_ranges, None);
+    }
+
+    #[test]
+    fn test_empty_bibtex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.bib", "")])
+            .main("main.bib")
+            .line(0)
+            .character(0)
+            .build()
+            .linked_editing_range();
+
+        let actual_ranges = find_linked_editing_ranges(request, CancellationToken::none());
+        assert_eq!(actual_ranges, None);
+    }
+
+    #[test]
+    fn test_begin() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\begin{itemize}\n\\item foo\n\\end{itemize}",
+            )])
+            .main("main.tex")
+            .line(0)
+            .character(9)
+            .build()
+            .linked_editing_range();
+
+        let actual_ranges = find_linked_editing_ranges(request, CancellationToken::none()).unwrap();
+        assert_eq!(
+            actual_ranges.ranges,
+            vec![
+                Range::new_simple(0, 7, 0, 14),
+                Range::new_simple(2, 5, 2, 12)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_end_nested() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\begin{foo}\n\\begin{bar}\n\\end{bar}\n\\end{foo}",
+            )])
+            .main("main.tex")
+            .line(2)
+            .character(8)
+            .build()
+            .linked_editing_range();
+
+        let actual_ranges = find_linked_editing_ranges(request, CancellationToken::none()).unwrap();
+        assert_eq!(
+            actual_ranges.ranges,
+            vec![
+                Range::new_simple(1, 7, 1, 10),
+                Range::new_simple(2, 5, 2, 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_name() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "\\begin{}\n\\end{}")])
+            .main("main.tex")
+            .line(0)
+            .character(7)
+            .build()
+            .linked_editing_range();
+
+        let actual_ranges = find_linked_editing_ranges(request, CancellationToken::none()).unwrap();
+        assert_eq!(
+            actual_ranges.ranges,
+            vec![Range::new_simple(0, 7, 0, 7), Range::new_simple(1, 5, 1, 5)]
+        );
+    }
+
+    #[test]
+    fn test_mismatched_names() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "\\begin{foo}\n\\end{bar}")])
+            .main("main.tex")
+            .line(0)
+            .character(8)
+            .build()
+            .linked_editing_range();
+
+        let actual_ranges = find_linked_editing_ranges(request, CancellationToken::none());
+        assert_eq!(actual_ranges, None);
+    }
+
+    #[test]
+    fn test_outside_of_name() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "\\begin{foo}\n\\end{foo}")])
+            .main("main.tex")
+            .line(0)
+            .character(2)
+            .build()
+            .linked_editing_range();
+
+        let actual_ranges = find_linked_editing_ranges(request, CancellationToken::none());
+        assert_eq!(actual_ranges, None);
+    }
+}
@@ -0,0 +1,125 @@
+use cancellation::CancellationToken;
+use lsp_types::{Location, ReferenceParams};
+
+use crate::{
+    features::cursor::CursorContext,
+    syntax::{latex, CstNode},
+    LineIndexExt,
+};
+
+pub fn find_command_references(
+    context: &CursorContext<ReferenceParams>,
+    cancellation_token: &CancellationToken,
+    references: &mut Vec<Location>,
+) -> Option<()> {
+    cancellation_token.result().ok()?;
+
+    let (name_text, _) = context.find_command_name()?;
+
+    for document in &context.request.subset.documents {
+        cancellation_token.result().ok()?;
+        if let Some(data) = document.data.as_latex() {
+            for node in data.root.descendants() {
+                let name = latex::GenericCommand::cast(node)
+                    .and_then(|command| command.name())
+                    .or_else(|| {
+                        latex::CommandDefinition::cast(node)
+                            .filter(|_| context.request.params.context.include_declaration)
+                            .and_then(|def| def.name())
+                            .and_then(|name| name.command())
+                    })
+                    .filter(|name| name.text() == name_text);
+
+                if let Some(name) = name {
+                    references.push(Location::new(
+                        document.uri.as_ref().clone().into(),
+                        document.line_index.line_col_lsp_range(name.text_range()),
+                    ));
+                }
+            }
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Range;
+
+    use crate::{features::testing::FeatureTester, RangeExt};
+
+    use super::*;
+
+    #[test]
+    fn test_definition() {
+        let tester = FeatureTester::builder()
+            .files(vec![
+                ("foo.tex", r#"\newcommand{\foo}{bar}"#),
+                ("bar.tex", r#"\foo\input{foo.tex}"#),
+            ])
+            .main("foo.tex")
+            .line(0)
+            .character(13)
+            .build();
+        let uri = tester.uri("bar.tex");
+        let mut actual_references = Vec::new();
+
+        let request = tester.reference();
+        let context = CursorContext::new(request);
+        find_command_references(&context, CancellationToken::none(), &mut actual_references);
+
+        let expected_references = vec![Location::new(
+            uri.as_ref().clone().into(),
+            Range::new_simple(0, 0, 0, 4),
+        )];
+        assert_eq!(actual_references, expected_references);
+    }
+
+    #[test]
+    fn test_reference_include_declaration() {
+        let tester = FeatureTester::builder()
+            .files(vec![
+                ("foo.tex", r#"\newcommand{\foo}{bar}"#),
+                ("bar.tex", r#"\foo\input{foo.tex}"#),
+            ])
+            .main("bar.tex")
+            .line(0)
+            .character(2)
+            .include_declaration(true)
+            .build();
+        let uri1 = tester.uri("foo.tex");
+        let uri2 = tester.uri("bar.tex");
+        let mut actual_references = Vec::new();
+
+        let request = tester.reference();
+        let context = CursorContext::new(request);
+        find_command_references(&context, CancellationToken::none(), &mut actual_references);
+
+        let expected_references = vec![
+            Location::new(uri2.as_ref().clone().into(), Range::new_simple(0, 0, 0, 4)),
+            Location::new(
+                uri1.as_ref().clone().into(),
+                Range::new_simple(0, 12, 0, 16),
+            ),
+        ];
+        assert_eq!(actual_references, expected_references);
+    }
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .line(0)
+            .character(0)
+            .build()
+            .reference();
+        let mut actual_references = Vec::new();
+
+        let context = CursorContext::new(request);
+        find_command_references(&context, CancellationToken::none(), &mut actual_references);
+
+        assert!(actual_references.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use cancellation::CancellationToken;
+use cstree::{TextRange, TextSize};
+use lsp_types::{SelectionRange, SelectionRangeParams};
+
+use crate::{
+    syntax::{bibtex, latex, CstNode},
+    DocumentData, LineIndex, LineIndexExt,
+};
+
+use super::FeatureRequest;
+
+pub fn find_selection_ranges(
+    request: FeatureRequest<SelectionRangeParams>,
+    token: &CancellationToken,
+) -> Vec<SelectionRange> {
+    let main_document = request.main_document();
+    let mut selection_ranges = Vec::new();
+    for position in &request.params.positions {
+        if token.is_canceled() {
+            break;
+        }
+
+        let offset = main_document.line_index.offset_lsp(*position);
+        let ranges = match &main_document.data {
+            DocumentData::Latex(data) => find_latex_ranges(&data.root, offset),
+            DocumentData::Bibtex(data) => find_bibtex_ranges(&data.root, offset),
+            DocumentData::BuildLog(_) => Vec::new(),
+        };
+
+        selection_ranges.push(create_selection_range(
+            &main_document.line_index,
+            offset,
+            ranges,
+        ));
+    }
+
+    selection_ranges
+}
+
+fn find_latex_ranges(root: &latex::SyntaxNode, offset: TextSize) -> Vec<TextRange> {
+    let is_trivia =
+        |token: &latex::SyntaxToken| matches!(token.kind(), latex::WHITESPACE | latex::COMMENT);
+
+    let token = match root
+        .token_at_offset(offset)
+        .right_biased()
+        .filter(|token| !is_trivia(token))
+        .or_else(|| root.token_at_offset(offset).left_biased())
+    {
+        Some(token) => token,
+        None => return Vec::new(),
+    };
+
+    let mut ranges = Vec::new();
+    if !is_trivia(&token) {
+        ranges.push(token.text_range());
+    }
+
+    for node in token.parent().ancestors() {
+        if let Some(environment) = latex::Environment::cast(node) {
+            ranges.extend(find_environment_body(environment));
+        }
+
+        let start = node.text_range().start();
+        let mut end = start;
+        let mut last_token = node.last_token();
+        while let Some(current) = last_token.filter(|token| token.text_range().start() >= start) {
+            if !is_trivia(current) {
+                end = current.text_range().end();
+                break;
+            }
+            last_token = current.prev_token();
+        }
+        ranges.push(TextRange::new(start, end));
+    }
+
+    ranges
+}
+
+fn find_environment_body(environment: latex::Environment) -> Option<TextRange> {
+    let start = environment.begin()?.small_range().end();
+    let end = environment.end()?.syntax().text_range().start();
+    let body = environment
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !matches!(token.kind(), latex::WHITESPACE | latex::COMMENT))
+        .map(|token| token.text_range())
+        .filter(|range| range.start() >= start && range.end() <= end)
+        .reduce(|range1, range2| range1.cover(range2))?;
+
+    Some(body)
+}
+
+fn find_bibtex_ranges(root: &bibtex::SyntaxNode, offset: TextSize) -> Vec<TextRange> {
+    let is_trivia =
+        |token: &bibtex::SyntaxToken| matches!(token.kind(), bibtex::WHITESPACE | bibtex::JUNK);
+
+    let token = match root
+        .token_at_offset(offset)
+        .right_biased()
+        .filter(|token| !is_trivia(token))
+        .or_else(|| root.token_at_offset(offset).left_biased())
+    {
+        Some(token) => token,
+        None => return Vec::new(),
+    };
+
+    let mut ranges = Vec::new();
+    if !is_trivia(&token) {
+        ranges.push(token.text_range());
+    }
+
+    for node in token.parent().ancestors() {
+        let start = node.text_range().start();
+        let mut end = start;
+        let mut last_token = node.last_token();
+        while let Some(current) = last_token.filter(|token| token.text_range().start() >= start) {
+            if !is_trivia(current) {
+                end = current.text_range().end();
+                break;
+            }
+            last_token = current.prev_token();
+        }
+        ranges.push(TextRange::new(start, end));
+    }
+
+    ranges
+}
+
+fn create_selection_range(
+    line_index: &LineIndex,
+    offset: TextSize,
+    ranges: Vec<TextRange>,
+) -> SelectionRange {
+    let mut previous = TextRange::empty(offset);
+    let mut ranges_to_keep = Vec::new();
+    for range in ranges {
+        if range != previous && range.contains_range(previous) {
+            ranges_to_keep.push(range);
+            previous = range;
+        }
+    }
+
+    if ranges_to_keep.is_empty() {
+        ranges_to_keep.push(previous);
+    }
+
+    let mut selection_range = None;
+    for range in ranges_to_keep.into_iter().rev() {
+        selection_range = Some(SelectionRange {
+            range: line_index.line_col_lsp_range(range),
+            parent: selection_range.map(Box::new),
+        });
+    }
+
+    selection_range.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use lsp_types::Range;
+
+    use crate::{features::testing::FeatureTester, RangeExt};
+
+    use super::*;
+
+    fn flatten(selection_range: &SelectionRange) -> Vec<Range> {
+        let mut ranges = vec![selection_range.range];
+        let mut parent = selection_range.parent.as_deref();
+        while let Some(current) = parent {
+            ranges.push(current.range);
+            parent = current.parent.as_deref();
+        }
+        ranges
+    }
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .line(0)
+            .character(0)
+            .build()
+            .selection_range();
+
+        let actual_ranges = find_selection_ranges(request, CancellationToken::none());
+        assert_eq!(actual_ranges.len(), 1);
+        assert_eq!(
+            flatten(&actual_ranges[0]),
+            vec![Range::new_simple(0, 0, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_latex() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                indoc! {r#"
+                    \section{Foo}
+                    \begin{document}
+                        \textbf{bar baz}
+                        qux
+                    \end{document}
+                "#},
+            )])
+            .main("main.tex")
+            .line(2)
+            .character(13)
+            .build()
+            .selection_range();
+
+        let actual_ranges = find_selection_ranges(request, CancellationToken::none());
+        assert_eq!(
+            flatten(&actual_ranges[0]),
+            vec![
+                Range::new_simple(2, 12, 2, 15),
+                Range::new_simple(2, 12, 2, 19),
+                Range::new_simple(2, 11, 2, 20),
+                Range::new_simple(2, 4, 2, 20),
+                Range::new_simple(2, 4, 3, 7),
+                Range::new_simple(1, 0, 4, 14),
+                Range::new_simple(0, 0, 4, 14),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bibtex() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.bib",
+                indoc! {r#"
+                    @article{foo,
+                        title = {bar baz}
+                    }
+                "#},
+            )])
+            .main("main.bib")
+            .line(1)
+            .character(14)
+            .build()
+            .selection_range();
+
+        let actual_ranges = find_selection_ranges(request, CancellationToken::none());
+        let actual_ranges = flatten(&actual_ranges[0]);
+        assert_eq!(actual_ranges[0], Range::new_simple(1, 13, 1, 16));
+        assert!(actual_ranges.contains(&Range::new_simple(1, 12, 1, 21)));
+        assert!(actual_ranges.contains(&Range::new_simple(1, 4, 1, 21)));
+        assert_eq!(actual_ranges.last(), Some(&Range::new_simple(0, 0, 2, 1)));
+    }
+}
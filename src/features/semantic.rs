@@ -1,21 +1,349 @@
 mod bibtex;
+mod latex;
 pub mod legend;
 
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
 use cancellation::CancellationToken;
-use lsp_types::{SemanticTokens, SemanticTokensRangeParams};
+use cstree::{TextRange, TextSize};
+use lsp_types::{
+    SemanticToken, SemanticTokenType, SemanticTokens, SemanticTokensDelta,
+    SemanticTokensDeltaParams, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+    SemanticTokensParams, SemanticTokensRangeParams, SemanticTokensResult,
+};
+use rustc_hash::FxHashMap;
+
+use crate::{DocumentData, LineIndex, LineIndexExt, Uri};
 
-use self::bibtex::find_bibtex_semantic_tokens_range;
+use self::{bibtex::find_bibtex_semantic_tokens, latex::find_latex_semantic_tokens, legend::*};
 
 use super::FeatureRequest;
 
+struct Context<'a> {
+    line_index: &'a LineIndex,
+    previous_line: u32,
+    previous_character: u32,
+    tokens: &'a mut Vec<SemanticToken>,
+    cancellation_token: &'a CancellationToken,
+}
+
+impl<'a> Context<'a> {
+    pub fn insert(
+        &mut self,
+        range: TextRange,
+        token_type: SemanticTokenType,
+        token_modifiers_bitset: ModifierSet,
+    ) {
+        let range = self.line_index.line_col_lsp_range(range);
+
+        let mut delta_line = range.start.line;
+        let mut delta_start = range.start.character;
+        if !self.tokens.is_empty() {
+            delta_line -= self.previous_line;
+            if delta_line == 0 {
+                delta_start -= self.previous_character;
+            }
+        }
+        self.tokens.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: range.end.character - range.start.character,
+            token_type: type_index(token_type),
+            token_modifiers_bitset: token_modifiers_bitset.0,
+        });
+
+        self.previous_line = range.start.line;
+        self.previous_character = range.start.character;
+    }
+}
+
+/// Remembers the last tokens sent for every document
+/// so that `textDocument/semanticTokens/full/delta` can respond with an edit.
+#[derive(Debug, Default)]
+pub struct SemanticTokensCache {
+    next_result_id: AtomicU64,
+    tokens_by_uri: Mutex<FxHashMap<Uri, (String, Vec<SemanticToken>)>>,
+}
+
+impl SemanticTokensCache {
+    fn insert(&self, uri: &Uri, tokens: Vec<SemanticToken>) -> String {
+        let result_id = self
+            .next_result_id
+            .fetch_add(1, Ordering::SeqCst)
+            .to_string();
+
+        self.tokens_by_uri
+            .lock()
+            .unwrap()
+            .insert(uri.clone(), (result_id.clone(), tokens));
+
+        result_id
+    }
+
+    fn get(&self, uri: &Uri, result_id: &str) -> Option<Vec<SemanticToken>> {
+        self.tokens_by_uri
+            .lock()
+            .unwrap()
+            .get(uri)
+            .filter(|(id, _)| id == result_id)
+            .map(|(_, tokens)| tokens.clone())
+    }
+
+    pub fn remove(&self, uri: &Uri) {
+        self.tokens_by_uri.lock().unwrap().remove(uri);
+    }
+}
+
 pub fn find_semantic_tokens_range(
     request: FeatureRequest<SemanticTokensRangeParams>,
     cancellation_token: &CancellationToken,
 ) -> Option<SemanticTokens> {
-    let mut data = Vec::new();
-    find_bibtex_semantic_tokens_range(&request, &mut data, cancellation_token);
+    let document = request.main_document();
+    let range = document.line_index.offset_lsp_range(request.params.range);
+    let data = find_semantic_tokens(&request, range, cancellation_token);
     Some(SemanticTokens {
         result_id: None,
         data,
     })
 }
+
+pub fn find_semantic_tokens_full(
+    request: FeatureRequest<SemanticTokensParams>,
+    cache: &SemanticTokensCache,
+    cancellation_token: &CancellationToken,
+) -> Option<SemanticTokensResult> {
+    let document = request.main_document();
+    let range = TextRange::up_to(TextSize::of(document.text.as_str()));
+    let data = find_semantic_tokens(&request, range, cancellation_token);
+    let result_id = cache.insert(&document.uri, data.clone());
+    Some(SemanticTokensResult::Tokens(SemanticTokens {
+        result_id: Some(result_id),
+        data,
+    }))
+}
+
+pub fn find_semantic_tokens_full_delta(
+    request: FeatureRequest<SemanticTokensDeltaParams>,
+    cache: &SemanticTokensCache,
+    cancellation_token: &CancellationToken,
+) -> Option<SemanticTokensFullDeltaResult> {
+    let document = request.main_document();
+    let range = TextRange::up_to(TextSize::of(document.text.as_str()));
+    let data = find_semantic_tokens(&request, range, cancellation_token);
+    let previous_data = cache.get(&document.uri, &request.params.previous_result_id);
+    let result_id = cache.insert(&document.uri, data.clone());
+    match previous_data {
+        Some(previous_data) => Some(SemanticTokensFullDeltaResult::TokensDelta(
+            SemanticTokensDelta {
+                result_id: Some(result_id),
+                edits: diff_tokens(&previous_data, &data),
+            },
+        )),
+        None => Some(SemanticTokensFullDeltaResult::Tokens(SemanticTokens {
+            result_id: Some(result_id),
+            data,
+        })),
+    }
+}
+
+fn find_semantic_tokens<P>(
+    request: &FeatureRequest<P>,
+    range: TextRange,
+    cancellation_token: &CancellationToken,
+) -> Vec<SemanticToken> {
+    let document = request.main_document();
+    let mut tokens = Vec::new();
+    let mut context = Context {
+        line_index: &document.line_index,
+        previous_line: 0,
+        previous_character: 0,
+        tokens: &mut tokens,
+        cancellation_token,
+    };
+
+    match &document.data {
+        DocumentData::Latex(data) => {
+            find_latex_semantic_tokens(&mut context, &request.subset, data, range);
+        }
+        DocumentData::Bibtex(data) => {
+            find_bibtex_semantic_tokens(&mut context, data, range);
+        }
+        DocumentData::BuildLog(_) => {}
+    }
+
+    tokens
+}
+
+fn diff_tokens(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix_len = old
+        .iter()
+        .zip(new)
+        .take_while(|(old_token, new_token)| old_token == new_token)
+        .count();
+
+    let old = &old[prefix_len..];
+    let new = &new[prefix_len..];
+    let suffix_len = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(old_token, new_token)| old_token == new_token)
+        .count();
+
+    let deleted = &old[..old.len() - suffix_len];
+    let inserted = &new[..new.len() - suffix_len];
+    if deleted.is_empty() && inserted.is_empty() {
+        return Vec::new();
+    }
+
+    vec![SemanticTokensEdit {
+        start: 5 * prefix_len as u32,
+        delete_count: 5 * deleted.len() as u32,
+        data: Some(inserted.to_vec()),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::features::testing::FeatureTester;
+
+    use super::*;
+
+    fn create_token(delta_line: u32, delta_start: u32, length: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    fn test_full_latex() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                indoc! {r#"
+                    \newcommand{\foo}{bar}
+                    \foo \baz{qux} % quux
+                    \label{sec:a}\ref{sec:a}\cite{key}
+                    \begin{equation}x\end{equation}
+                "#},
+            )])
+            .main("main.tex")
+            .build()
+            .semantic_tokens_full();
+
+        let cache = SemanticTokensCache::default();
+        let tokens = match find_semantic_tokens_full(request, &cache, CancellationToken::none()) {
+            Some(SemanticTokensResult::Tokens(tokens)) => tokens.data,
+            _ => panic!("expected tokens"),
+        };
+
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|token| legend::SUPPORTED_TYPES[token.token_type as usize].clone())
+            .collect();
+
+        assert_eq!(
+            types,
+            vec![
+                PACKAGE_COMMAND,
+                USER_COMMAND,
+                USER_COMMAND,
+                GENERIC_COMMAND,
+                SemanticTokenType::COMMENT,
+                PACKAGE_COMMAND,
+                LABEL,
+                PACKAGE_COMMAND,
+                LABEL,
+                PACKAGE_COMMAND,
+                CITATION_KEY,
+                PACKAGE_COMMAND,
+                ENVIRONMENT_NAME,
+                MATH,
+                PACKAGE_COMMAND,
+                ENVIRONMENT_NAME,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_delta() {
+        let tester = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\label{foo}"#)])
+            .main("main.tex")
+            .build();
+
+        let cache = SemanticTokensCache::default();
+        let result_id = match find_semantic_tokens_full(
+            tester.clone().semantic_tokens_full(),
+            &cache,
+            CancellationToken::none(),
+        ) {
+            Some(SemanticTokensResult::Tokens(tokens)) => tokens.result_id.unwrap(),
+            _ => panic!("expected tokens"),
+        };
+
+        let actual_result = find_semantic_tokens_full_delta(
+            tester.semantic_tokens_full_delta(result_id),
+            &cache,
+            CancellationToken::none(),
+        );
+
+        match actual_result {
+            Some(SemanticTokensFullDeltaResult::TokensDelta(delta)) => {
+                assert!(delta.edits.is_empty());
+            }
+            _ => panic!("expected delta"),
+        }
+    }
+
+    #[test]
+    fn test_full_delta_unknown_result_id() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\label{foo}"#)])
+            .main("main.tex")
+            .build()
+            .semantic_tokens_full_delta("foo".into());
+
+        let cache = SemanticTokensCache::default();
+        let actual_result =
+            find_semantic_tokens_full_delta(request, &cache, CancellationToken::none());
+
+        assert!(matches!(
+            actual_result,
+            Some(SemanticTokensFullDeltaResult::Tokens(_))
+        ));
+    }
+
+    #[test]
+    fn test_diff_tokens() {
+        let old = vec![
+            create_token(0, 0, 1),
+            create_token(0, 2, 1),
+            create_token(1, 0, 1),
+        ];
+        let new = vec![
+            create_token(0, 0, 1),
+            create_token(0, 2, 3),
+            create_token(0, 4, 1),
+            create_token(1, 0, 1),
+        ];
+
+        assert_eq!(
+            diff_tokens(&old, &new),
+            vec![SemanticTokensEdit {
+                start: 5,
+                delete_count: 5,
+                data: Some(vec![create_token(0, 2, 3), create_token(0, 4, 1)]),
+            }]
+        );
+    }
+}
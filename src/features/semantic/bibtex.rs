@@ -1,83 +1,28 @@
-use cancellation::CancellationToken;
 use cstree::{NodeOrToken, TextRange};
-use lsp_types::{
-    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensRangeParams,
-};
+use lsp_types::{SemanticTokenModifier, SemanticTokenType};
 
 use crate::{
-    features::FeatureRequest,
     syntax::{bibtex, CstNode},
-    BibtexEntryTypeCategory, LineIndex, LineIndexExt, LANGUAGE_DATA,
+    BibtexDocumentData, BibtexEntryTypeCategory, LANGUAGE_DATA,
 };
 
-use super::legend::*;
-
-struct Context<'a> {
-    line_index: &'a LineIndex,
-    previous_line: u32,
-    previous_character: u32,
-    tokens: &'a mut Vec<SemanticToken>,
-    cancellation_token: &'a CancellationToken,
-}
-
-impl<'a> Context<'a> {
-    pub fn insert(
-        &mut self,
-        range: TextRange,
-        token_type: SemanticTokenType,
-        token_modifiers_bitset: ModifierSet,
-    ) {
-        let range = self.line_index.line_col_lsp_range(range);
-
-        let mut delta_line = range.start.line;
-        let mut delta_start = range.start.character;
-        if !self.tokens.is_empty() {
-            delta_line -= self.previous_line;
-            if delta_line == 0 {
-                delta_start -= self.previous_character;
-            }
-        }
-        self.tokens.push(SemanticToken {
-            delta_line,
-            delta_start,
-            length: range.end.character - range.start.character,
-            token_type: type_index(token_type),
-            token_modifiers_bitset: token_modifiers_bitset.0,
-        });
+use super::{legend::*, Context};
 
-        self.previous_line = range.start.line;
-        self.previous_character = range.start.character;
-    }
-}
-
-pub fn find_bibtex_semantic_tokens_range(
-    request: &FeatureRequest<SemanticTokensRangeParams>,
-    tokens: &mut Vec<SemanticToken>,
-    cancellation_token: &CancellationToken,
+pub fn find_bibtex_semantic_tokens(
+    context: &mut Context,
+    data: &BibtexDocumentData,
+    range: TextRange,
 ) -> Option<()> {
-    let document = request.main_document();
-    let data = document.data.as_bibtex()?;
-
-    let mut context = Context {
-        line_index: &document.line_index,
-        previous_line: 0,
-        previous_character: 0,
-        tokens,
-        cancellation_token,
-    };
-
-    let range = document.line_index.offset_lsp_range(request.params.range);
-
     for node in data
         .root
         .children()
         .filter(|node| node.text_range().intersect(range).is_some())
     {
-        cancellation_token.result().ok()?;
-        visit_junk(&mut context, node)
-            .or_else(|| visit_preamble(&mut context, node))
-            .or_else(|| visit_string(&mut context, node))
-            .or_else(|| visit_entry(&mut context, node));
+        context.cancellation_token.result().ok()?;
+        visit_junk(context, node)
+            .or_else(|| visit_preamble(context, node))
+            .or_else(|| visit_string(context, node))
+            .or_else(|| visit_entry(context, node));
     }
 
     Some(())
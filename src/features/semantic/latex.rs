@@ -0,0 +1,164 @@
+use cstree::TextRange;
+use lsp_types::{SemanticTokenModifier, SemanticTokenType};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    component_db::COMPONENT_DATABASE,
+    syntax::{latex, CstNode},
+    LatexDocumentData, WorkspaceSubset, LANGUAGE_DATA,
+};
+
+use super::{legend::*, Context};
+
+struct Commands<'a> {
+    user: FxHashSet<&'a str>,
+    package: FxHashSet<&'a str>,
+}
+
+impl<'a> Commands<'a> {
+    fn new(subset: &'a WorkspaceSubset) -> Self {
+        let user = subset
+            .documents
+            .iter()
+            .filter_map(|document| document.data.as_latex())
+            .flat_map(|data| data.root.descendants())
+            .filter_map(latex::CommandDefinition::cast)
+            .filter_map(|def| def.name()?.command())
+            .map(|name| name.text())
+            .collect();
+
+        let package = COMPONENT_DATABASE
+            .linked_components(subset)
+            .into_iter()
+            .flat_map(|component| component.commands.iter())
+            .map(|command| command.name.as_str())
+            .collect();
+
+        Self { user, package }
+    }
+}
+
+pub fn find_latex_semantic_tokens(
+    context: &mut Context,
+    subset: &WorkspaceSubset,
+    data: &LatexDocumentData,
+    range: TextRange,
+) -> Option<()> {
+    let commands = Commands::new(subset);
+    for token in data
+        .root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.text_range().intersect(range).is_some())
+    {
+        context.cancellation_token.result().ok()?;
+        if let Some((token_type, modifiers)) = classify_token(&commands, token) {
+            context.insert(token.text_range(), token_type, modifiers);
+        }
+    }
+
+    Some(())
+}
+
+fn classify_token(
+    commands: &Commands,
+    token: &latex::SyntaxToken,
+) -> Option<(SemanticTokenType, ModifierSet)> {
+    match token.kind() {
+        latex::COMMENT => Some((SemanticTokenType::COMMENT, ModifierSet::default())),
+        latex::GENERIC_COMMAND_NAME => Some(classify_generic_command(commands, token)),
+        latex::BEGIN_EQUATION_NAME | latex::END_EQUATION_NAME | latex::DOLLAR => {
+            Some((MATH, ModifierSet::default()))
+        }
+        kind if kind.is_command_name() => Some((PACKAGE_COMMAND, ModifierSet::default())),
+        latex::WORD => classify_word(token),
+        _ => None,
+    }
+}
+
+fn classify_generic_command(
+    commands: &Commands,
+    token: &latex::SyntaxToken,
+) -> (SemanticTokenType, ModifierSet) {
+    let name = token.text();
+    if commands.user.contains(name) {
+        let parent = token.parent();
+        let is_definition = parent.kind() == latex::CURLY_GROUP_COMMAND
+            && parent
+                .parent()
+                .map_or(false, |node| node.kind() == latex::COMMAND_DEFINITION);
+
+        if is_definition {
+            (
+                USER_COMMAND,
+                ModifierSet::default() | SemanticTokenModifier::DEFINITION,
+            )
+        } else {
+            (USER_COMMAND, ModifierSet::default())
+        }
+    } else if commands.package.contains(&name[1..]) {
+        (PACKAGE_COMMAND, ModifierSet::default())
+    } else {
+        (GENERIC_COMMAND, ModifierSet::default())
+    }
+}
+
+fn classify_word(token: &latex::SyntaxToken) -> Option<(SemanticTokenType, ModifierSet)> {
+    let parent = token.parent();
+    let is_key = parent.kind() == latex::KEY
+        && parent.parent().map_or(false, |group| {
+            matches!(
+                group.kind(),
+                latex::CURLY_GROUP_WORD | latex::CURLY_GROUP_WORD_LIST
+            )
+        });
+
+    for node in parent.ancestors() {
+        match node.kind() {
+            latex::LABEL_DEFINITION if is_key => {
+                return Some((
+                    LABEL,
+                    ModifierSet::default() | SemanticTokenModifier::DEFINITION,
+                ));
+            }
+            latex::LABEL_REFERENCE | latex::LABEL_REFERENCE_RANGE | latex::LABEL_NUMBER
+                if is_key =>
+            {
+                return Some((LABEL, ModifierSet::default()));
+            }
+            latex::CITATION if is_key => {
+                return Some((CITATION_KEY, ModifierSet::default()));
+            }
+            latex::BEGIN | latex::END if is_key => {
+                return Some((ENVIRONMENT_NAME, ModifierSet::default()));
+            }
+            latex::ENVIRONMENT_DEFINITION if is_key => {
+                return Some((
+                    ENVIRONMENT_NAME,
+                    ModifierSet::default() | SemanticTokenModifier::DEFINITION,
+                ));
+            }
+            latex::FORMULA | latex::EQUATION => {
+                return Some((MATH, ModifierSet::default()));
+            }
+            latex::ENVIRONMENT if is_math_environment(node) => {
+                return Some((MATH, ModifierSet::default()));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn is_math_environment(node: &latex::SyntaxNode) -> bool {
+    latex::Environment::cast(node)
+        .and_then(|environment| environment.begin()?.name()?.key())
+        .map_or(false, |name| {
+            let name = name.to_string();
+            LANGUAGE_DATA
+                .math_environments
+                .iter()
+                .any(|math_environment| math_environment == &name)
+        })
+}
@@ -38,6 +38,12 @@ define_semantic_token_types![
     (HASH, "hash"),
     (QUOTE, "quote"),
     (GENERIC_COMMAND, "genericCommand"),
+    (USER_COMMAND, "userCommand"),
+    (PACKAGE_COMMAND, "packageCommand"),
+    (LABEL, "label"),
+    (CITATION_KEY, "citationKey"),
+    (ENVIRONMENT_NAME, "environmentName"),
+    (MATH, "math"),
 ];
 
 macro_rules! define_semantic_token_modifiers {
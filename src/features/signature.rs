@@ -0,0 +1,427 @@
+use cancellation::CancellationToken;
+use lsp_types::{
+    Documentation, ParameterInformation, ParameterLabel, SignatureHelp, SignatureHelpParams,
+    SignatureInformation,
+};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    component_db::COMPONENT_DATABASE,
+    features::cursor::CursorContext,
+    syntax::{
+        latex::{self, HasBrack},
+        CstNode,
+    },
+};
+
+use super::FeatureRequest;
+
+pub fn find_signature_help(
+    request: FeatureRequest<SignatureHelpParams>,
+    token: &CancellationToken,
+) -> Option<SignatureHelp> {
+    let context = CursorContext::new(request);
+    log::debug!("[SignatureHelp] Cursor: {:?}", context.cursor);
+
+    let (signatures, index) = match context.find_command_argument() {
+        Some((name, index)) => (find_command_signatures(&context, &name, token), index),
+        None => {
+            let (name, index) = context.find_environment_argument()?;
+            (find_environment_signatures(&context, &name, token), index)
+        }
+    };
+
+    let mut labels = FxHashSet::default();
+    let signatures: Vec<_> = signatures
+        .into_iter()
+        .filter_map(|signature| signature.into_information(index))
+        .filter(|signature| labels.insert(signature.label.clone()))
+        .collect();
+
+    let active_parameter = signatures.first()?.active_parameter;
+    Some(SignatureHelp {
+        signatures,
+        active_signature: Some(0),
+        active_parameter,
+    })
+}
+
+#[derive(Debug)]
+struct Parameter {
+    optional: bool,
+    documentation: Option<String>,
+}
+
+#[derive(Debug)]
+struct Signature {
+    name: String,
+    parameters: Vec<Parameter>,
+}
+
+impl Signature {
+    fn from_definition(name: String, count: usize, default: Option<String>) -> Self {
+        let parameters = (0..count)
+            .map(|i| match &default {
+                Some(default) if i == 0 => Parameter {
+                    optional: true,
+                    documentation: Some(format!("Optional (default: {})", default)),
+                },
+                _ => Parameter {
+                    optional: false,
+                    documentation: None,
+                },
+            })
+            .collect();
+
+        Self { name, parameters }
+    }
+
+    /// Creates the signature if it accepts the mandatory argument at `index`.
+    /// Optional arguments are written in brackets, so they do not count towards `index`.
+    fn into_information(self, index: usize) -> Option<SignatureInformation> {
+        let optional_count = self
+            .parameters
+            .iter()
+            .filter(|parameter| parameter.optional)
+            .count();
+
+        let active_parameter = optional_count + index;
+        if active_parameter >= self.parameters.len() {
+            return None;
+        }
+
+        let mut label = self.name;
+        let parameters = self
+            .parameters
+            .into_iter()
+            .enumerate()
+            .map(|(i, parameter)| {
+                let (left, right) = if parameter.optional {
+                    ('[', ']')
+                } else {
+                    ('{', '}')
+                };
+
+                let parameter_label = format!("#{}", i + 1);
+                label.push(left);
+                label.push_str(&parameter_label);
+                label.push(right);
+
+                ParameterInformation {
+                    label: ParameterLabel::Simple(parameter_label),
+                    documentation: parameter.documentation.map(Documentation::String),
+                }
+            })
+            .collect();
+
+        Some(SignatureInformation {
+            label,
+            documentation: None,
+            parameters: Some(parameters),
+            active_parameter: Some(active_parameter as u32),
+        })
+    }
+}
+
+fn find_command_signatures(
+    context: &CursorContext<SignatureHelpParams>,
+    name: &str,
+    token: &CancellationToken,
+) -> Vec<Signature> {
+    let mut signatures = Vec::new();
+    for document in &context.request.subset.documents {
+        if token.is_canceled() {
+            return Vec::new();
+        }
+
+        if let Some(data) = document.data.as_latex() {
+            for def in data
+                .root
+                .descendants()
+                .filter_map(latex::CommandDefinition::cast)
+                .filter(|def| {
+                    def.name()
+                        .and_then(|name| name.command())
+                        .map_or(false, |command| command.text() == name)
+                })
+            {
+                if let Some(count) = def.argument_count().and_then(parse_argument_count) {
+                    let default = def.default_argument().and_then(|arg| arg.content_text());
+                    signatures.push(Signature::from_definition(name.to_string(), count, default));
+                }
+            }
+        }
+    }
+
+    let command_name = &name[1..];
+    for component in COMPONENT_DATABASE.linked_components(&context.request.subset) {
+        for command in component
+            .commands
+            .iter()
+            .filter(|command| command.name == command_name)
+        {
+            let parameters = command
+                .parameters
+                .iter()
+                .map(|param| {
+                    let args: Vec<_> = param.0.iter().map(|arg| arg.name.as_str()).collect();
+                    Parameter {
+                        optional: false,
+                        documentation: Some(args.join(", ")).filter(|args| !args.is_empty()),
+                    }
+                })
+                .collect();
+
+            signatures.push(Signature {
+                name: name.to_string(),
+                parameters,
+            });
+        }
+    }
+
+    signatures
+}
+
+fn find_environment_signatures(
+    context: &CursorContext<SignatureHelpParams>,
+    name: &str,
+    token: &CancellationToken,
+) -> Vec<Signature> {
+    let mut signatures = Vec::new();
+    for document in &context.request.subset.documents {
+        if token.is_canceled() {
+            return Vec::new();
+        }
+
+        if let Some(data) = document.data.as_latex() {
+            for def in data
+                .root
+                .descendants()
+                .filter_map(latex::EnvironmentDefinition::cast)
+                .filter(|def| {
+                    def.name()
+                        .and_then(|name| name.key())
+                        .map_or(false, |key| key.to_string() == name)
+                })
+            {
+                if let Some(count) = def.argument_count().and_then(parse_argument_count) {
+                    let default = def.default_argument().and_then(|arg| arg.content_text());
+                    let label = format!("\\begin{{{}}}", name);
+                    signatures.push(Signature::from_definition(label, count, default));
+                }
+            }
+        }
+    }
+
+    signatures
+}
+
+fn parse_argument_count(group: latex::BrackGroupWord) -> Option<usize> {
+    group.key()?.to_string().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::features::testing::FeatureTester;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_latex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", "")])
+            .main("main.tex")
+            .line(0)
+            .character(0)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none());
+        assert_eq!(actual_help, None);
+    }
+
+    #[test]
+    fn test_empty_bibtex_document() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.bib", "")])
+            .main("main.bib")
+            .line(0)
+            .character(0)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none());
+        assert_eq!(actual_help, None);
+    }
+
+    #[test]
+    fn test_user_command() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newcommand{\\foo}[2]{#1 #2}\n\\foo{bar}{baz}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(11)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none()).unwrap();
+        assert_eq!(actual_help.active_parameter, Some(1));
+        assert_eq!(actual_help.signatures.len(), 1);
+        assert_eq!(actual_help.signatures[0].label, "\\foo{#1}{#2}");
+        assert_eq!(
+            actual_help.signatures[0].parameters.as_ref().unwrap()[1].label,
+            ParameterLabel::Simple("#2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_user_command_too_many_arguments() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newcommand{\\foo}[1]{#1}\n\\foo{bar}{baz}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(11)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none());
+        assert_eq!(actual_help, None);
+    }
+
+    #[test]
+    fn test_user_command_outside_of_group() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newcommand{\\foo}[2]{#1 #2}\n\\foo{bar}{baz}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(4)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none());
+        assert_eq!(actual_help, None);
+    }
+
+    #[test]
+    fn test_component_command() {
+        let request = FeatureTester::builder()
+            .files(vec![("main.tex", r#"\mathbb{}\usepackage{amsfonts}"#)])
+            .main("main.tex")
+            .line(0)
+            .character(8)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none()).unwrap();
+        assert_eq!(actual_help.active_parameter, Some(0));
+        assert_eq!(actual_help.signatures[0].label, "\\mathbb{#1}");
+    }
+
+    #[test]
+    fn test_user_environment() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newenvironment{foo}[2]{}{}\n\\begin{foo}{bar}{baz}\n\\end{foo}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(13)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none()).unwrap();
+        assert_eq!(actual_help.active_parameter, Some(0));
+        assert_eq!(actual_help.signatures[0].label, "\\begin{foo}{#1}{#2}");
+    }
+
+    #[test]
+    fn test_user_command_optional_argument() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newcommand{\\foo}[2][x]{#1 #2}\n\\foo{bar}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(6)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none()).unwrap();
+        assert_eq!(actual_help.active_parameter, Some(1));
+        assert_eq!(actual_help.signatures.len(), 1);
+        assert_eq!(actual_help.signatures[0].label, "\\foo[#1]{#2}");
+        assert_eq!(
+            actual_help.signatures[0].parameters.as_ref().unwrap()[0].documentation,
+            Some(Documentation::String("Optional (default: x)".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_user_command_optional_argument_given() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newcommand{\\foo}[2][x]{#1 #2}\n\\foo[y]{bar}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(9)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none()).unwrap();
+        assert_eq!(actual_help.active_parameter, Some(1));
+        assert_eq!(actual_help.signatures[0].label, "\\foo[#1]{#2}");
+    }
+
+    #[test]
+    fn test_user_command_duplicate_definitions() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newcommand{\\foo}[1]{}\\newcommand{\\foo}[2]{}\\newcommand{\\foo}[1]{}\n\\foo{bar}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(6)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none()).unwrap();
+        let labels: Vec<_> = actual_help
+            .signatures
+            .iter()
+            .map(|signature| signature.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["\\foo{#1}", "\\foo{#1}{#2}"]);
+    }
+
+    #[test]
+    fn test_user_environment_optional_argument() {
+        let request = FeatureTester::builder()
+            .files(vec![(
+                "main.tex",
+                "\\newenvironment{foo}[2][x]{}{}\n\\begin{foo}[y]{bar}\n\\end{foo}",
+            )])
+            .main("main.tex")
+            .line(1)
+            .character(16)
+            .build()
+            .signature_help();
+
+        let actual_help = find_signature_help(request, CancellationToken::none()).unwrap();
+        assert_eq!(actual_help.active_parameter, Some(1));
+        assert_eq!(actual_help.signatures[0].label, "\\begin{foo}[#1]{#2}");
+    }
+}
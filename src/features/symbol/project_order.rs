@@ -3,7 +3,7 @@ use std::{sync::Arc, usize};
 use petgraph::{algo::tarjan_scc, Directed, Graph};
 use rustc_hash::FxHashSet;
 
-use crate::{Uri, Workspace, WorkspaceSubset};
+use crate::{features::dependency_graph::resolve_link, Uri, Workspace, WorkspaceSubset};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ProjectOrdering {
@@ -86,15 +86,8 @@ fn build_dependency_graph(subset: &WorkspaceSubset) -> Graph<usize, (), Directed
     for (i, document) in subset.documents.iter().enumerate() {
         if let Some(data) = document.data.as_latex() {
             for link in &data.extras.explicit_links {
-                for target in &link.targets {
-                    if let Some(j) = subset
-                        .documents
-                        .iter()
-                        .position(|document| document.uri.as_ref() == target.as_ref())
-                    {
-                        graph.add_edge(nodes[j], nodes[i], ());
-                        break;
-                    }
+                if let Some(j) = resolve_link(&subset.documents, link) {
+                    graph.add_edge(nodes[j], nodes[i], ());
                 }
             }
         }
@@ -0,0 +1,182 @@
+use std::{fs, path::Path, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use cancellation::CancellationToken;
+use lsp_types::{
+    DocumentFormattingParams, FormattingOptions, TextDocumentIdentifier, TextEdit,
+    WorkDoneProgressParams,
+};
+
+use crate::{
+    create_workspace_fast, encoding,
+    features::{format_source_code, FeatureRequest},
+    Document, DocumentLanguage, LineIndexExt, Options, ServerContext, Uri, Workspace,
+    WorkspaceSource,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FormattedFile {
+    pub old_text: String,
+    pub new_text: String,
+}
+
+impl FormattedFile {
+    pub fn is_changed(&self) -> bool {
+        self.old_text != self.new_text
+    }
+}
+
+/// Formats a BibTeX or LaTeX file with the formatters of the given options.
+/// Returns `None` if the file cannot be formatted.
+pub fn format_file(
+    path: &Path,
+    options: Options,
+    formatting_options: FormattingOptions,
+) -> Result<Option<FormattedFile>> {
+    let path = path.canonicalize()?;
+    let language = match DocumentLanguage::by_path(&path) {
+        Some(DocumentLanguage::Latex) => DocumentLanguage::Latex,
+        Some(DocumentLanguage::Bibtex) => DocumentLanguage::Bibtex,
+        _ => return Ok(None),
+    };
+
+    let text = encoding::decode(&fs::read(&path)?, options.default_encoding.as_deref());
+    let uri = Arc::new(
+        Uri::from_file_path(&path).map_err(|_| anyhow!("Invalid path: {}", path.display()))?,
+    );
+
+    let context = Arc::new(ServerContext::new(
+        path.parent().unwrap_or(&path).to_path_buf(),
+    ));
+    *context.options.write().unwrap() = options;
+
+    let workspace: Arc<dyn Workspace> = Arc::new(create_workspace_fast(Arc::clone(&context))?);
+    let document = workspace.open(Arc::clone(&uri), text, language, WorkspaceSource::Server);
+
+    let subset = match workspace.subset(Arc::clone(&uri)) {
+        Some(subset) => subset,
+        None => return Ok(None),
+    };
+
+    let request = FeatureRequest {
+        context,
+        params: DocumentFormattingParams {
+            text_document: TextDocumentIdentifier::new(uri.as_ref().clone().into()),
+            options: formatting_options,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        },
+        workspace: Arc::clone(&workspace),
+        subset,
+    };
+
+    let edits = match format_source_code(request, CancellationToken::none()) {
+        Some(edits) => edits,
+        None => return Ok(None),
+    };
+
+    Ok(Some(FormattedFile {
+        old_text: document.text.clone(),
+        new_text: apply_edits(&document, edits),
+    }))
+}
+
+/// Writes a formatted file using the encoding declared in the text.
+pub fn write_formatted_file(path: &Path, text: &str, options: &Options) -> Result<()> {
+    let data = encoding::encode(text, options.default_encoding.as_deref())?;
+    fs::write(path, data)?;
+    Ok(())
+}
+
+fn apply_edits(document: &Document, mut edits: Vec<TextEdit>) -> String {
+    let mut text = document.text.clone();
+    edits.sort_by_key(|edit| (edit.range.start.line, edit.range.start.character));
+    for edit in edits.into_iter().rev() {
+        let range = document.line_index.offset_lsp_range(edit.range);
+        text.replace_range(
+            usize::from(range.start())..usize::from(range.end()),
+            &edit.new_text,
+        );
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use tempfile::tempdir;
+
+    use crate::BibtexFormatter;
+
+    use super::*;
+
+    fn formatting_options() -> FormattingOptions {
+        FormattingOptions {
+            tab_size: 4,
+            insert_spaces: true,
+            ..FormattingOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_format_bibtex() {
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("refs.bib");
+        fs::write(&path, "@article{foo, author = {Bar},\ntitle = {Baz}}").unwrap();
+
+        let file = format_file(&path, Options::default(), formatting_options())
+            .unwrap()
+            .unwrap();
+        assert!(file.is_changed());
+        assert_eq!(
+            file.new_text,
+            indoc! {"
+                @article{foo,
+                    author = {Bar},
+                    title = {Baz},
+                }"}
+        );
+    }
+
+    #[test]
+    fn test_format_bibtex_line_length() {
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("refs.bib");
+        fs::write(&path, "@article{foo, title = {Foo Bar Baz Qux}}").unwrap();
+
+        let options = Options {
+            formatter_line_length: Some(20),
+            bibtex_formatter: BibtexFormatter::Texlab,
+            ..Options::default()
+        };
+
+        let wrapped_file = format_file(&path, options, formatting_options())
+            .unwrap()
+            .unwrap();
+        let file = format_file(&path, Options::default(), formatting_options())
+            .unwrap()
+            .unwrap();
+        assert!(wrapped_file.new_text.lines().count() > file.new_text.lines().count());
+    }
+
+    #[test]
+    fn test_format_unsupported() {
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("main.log");
+        fs::write(&path, "").unwrap();
+
+        let file = format_file(&path, Options::default(), formatting_options()).unwrap();
+        assert_eq!(file, None);
+    }
+
+    #[test]
+    fn test_write_formatted_file_unmappable_character() {
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("main.tex");
+        fs::write(&path, "").unwrap();
+
+        let text = "\\usepackage[latin1]{inputenc}\n\u{4e2d}";
+        assert!(write_formatted_file(&path, text, &Options::default()).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+}
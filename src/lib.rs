@@ -1,4 +1,5 @@
 mod capabilities;
+mod check;
 #[cfg(feature = "citation")]
 pub mod citation;
 mod client;
@@ -8,7 +9,9 @@ mod context;
 pub mod diagnostics;
 mod dispatch;
 pub mod distro;
+mod encoding;
 pub mod features;
+mod format;
 mod label;
 mod lang_data;
 mod language;
@@ -18,21 +21,29 @@ mod options;
 mod range;
 mod req_queue;
 mod server;
+mod stats;
 pub mod syntax;
 mod uri;
 mod workspace;
 
 pub use self::{
     capabilities::ClientCapabilitiesExt,
+    check::{check_project, CheckFormat, CheckReport, FileDiagnostics},
+    config::{
+        load_project_indent_options, load_project_options, ProjectIndentOptions,
+        PROJECT_CONFIG_FILE,
+    },
     context::ServerContext,
+    format::{format_file, write_formatted_file, FormattedFile},
     label::*,
     lang_data::*,
     language::DocumentLanguage,
-    line_index::{LineCol, LineColUtf16, LineIndex},
+    line_index::{LineCol, LineColUtf16, LineIndex, PositionEncoding},
     line_index_ext::LineIndexExt,
     options::*,
     range::RangeExt,
     server::Server,
+    stats::*,
     uri::Uri,
     workspace::*,
 };
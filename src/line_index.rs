@@ -7,12 +7,47 @@ use std::iter;
 use cstree::{TextRange, TextSize};
 use rustc_hash::FxHashMap;
 
+/// The unit in which the columns of LSP positions are measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PositionEncoding {
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+            Self::Utf32 => "utf-32",
+        }
+    }
+
+    pub fn from_label(value: &str) -> Option<Self> {
+        match value {
+            "utf-8" => Some(Self::Utf8),
+            "utf-16" => Some(Self::Utf16),
+            "utf-32" => Some(Self::Utf32),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PositionEncoding {
+    fn default() -> Self {
+        Self::Utf16
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LineIndex {
     /// Offset the the beginning of each line, zero-based
     pub(crate) newlines: Vec<TextSize>,
     /// List of non-ASCII characters on each line
     pub(crate) utf16_lines: FxHashMap<u32, Vec<Utf16Char>>,
+    /// Encoding of the columns of LSP positions
+    pub(crate) encoding: PositionEncoding,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -45,18 +80,22 @@ impl Utf16Char {
         self.end - self.start
     }
 
-    /// Returns the length in 16-bit UTF-16 code units.
-    fn len_utf16(&self) -> usize {
-        if self.len() == TextSize::from(4) {
-            2
-        } else {
-            1
+    /// Returns the length in code units of the given encoding.
+    fn len_encoded(&self, encoding: PositionEncoding) -> usize {
+        match encoding {
+            PositionEncoding::Utf8 => self.len().into(),
+            PositionEncoding::Utf16 if self.len() == TextSize::from(4) => 2,
+            PositionEncoding::Utf16 | PositionEncoding::Utf32 => 1,
         }
     }
 }
 
 impl LineIndex {
     pub fn new(text: &str) -> LineIndex {
+        Self::with_encoding(text, PositionEncoding::default())
+    }
+
+    pub fn with_encoding(text: &str, encoding: PositionEncoding) -> LineIndex {
         let mut utf16_lines = FxHashMap::default();
         let mut utf16_chars = Vec::new();
 
@@ -100,9 +139,18 @@ impl LineIndex {
         LineIndex {
             newlines,
             utf16_lines,
+            encoding,
         }
     }
 
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.newlines.len()
+    }
+
     pub fn line_col(&self, offset: TextSize) -> LineCol {
         let line = partition_point(&self.newlines, |&it| it <= offset) - 1;
         let line_start_offset = self.newlines[line];
@@ -118,15 +166,26 @@ impl LineIndex {
     }
 
     pub fn to_utf16(&self, line_col: LineCol) -> LineColUtf16 {
-        let col = self.utf8_to_utf16_col(line_col.line, line_col.col.into());
+        self.to_encoded(line_col, PositionEncoding::Utf16)
+    }
+
+    pub fn to_utf8(&self, line_col: LineColUtf16) -> LineCol {
+        self.to_decoded(line_col, PositionEncoding::Utf16)
+    }
+
+    /// Converts a position into the given encoding.
+    /// The column of the result is measured in code units of the encoding.
+    pub fn to_encoded(&self, line_col: LineCol, encoding: PositionEncoding) -> LineColUtf16 {
+        let col = self.utf8_to_encoded_col(line_col.line, line_col.col.into(), encoding);
         LineColUtf16 {
             line: line_col.line,
             col: col as u32,
         }
     }
 
-    pub fn to_utf8(&self, line_col: LineColUtf16) -> LineCol {
-        let col = self.utf16_to_utf8_col(line_col.line, line_col.col);
+    /// Converts a position whose column is measured in code units of the given encoding.
+    pub fn to_decoded(&self, line_col: LineColUtf16, encoding: PositionEncoding) -> LineCol {
+        let col = self.encoded_to_utf8_col(line_col.line, line_col.col, encoding);
         LineCol {
             line: line_col.line,
             col: col.into(),
@@ -146,12 +205,12 @@ impl LineIndex {
             .filter(|it| !it.is_empty())
     }
 
-    fn utf8_to_utf16_col(&self, line: u32, col: TextSize) -> usize {
+    fn utf8_to_encoded_col(&self, line: u32, col: TextSize, encoding: PositionEncoding) -> usize {
         let mut res: usize = col.into();
         if let Some(utf16_chars) = self.utf16_lines.get(&line) {
             for c in utf16_chars {
                 if c.end <= col {
-                    res -= usize::from(c.len()) - c.len_utf16();
+                    res -= usize::from(c.len()) - c.len_encoded(encoding);
                 } else {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account
@@ -162,16 +221,25 @@ impl LineIndex {
         res
     }
 
-    fn utf16_to_utf8_col(&self, line: u32, mut col: u32) -> TextSize {
+    fn encoded_to_utf8_col(&self, line: u32, mut col: u32, encoding: PositionEncoding) -> TextSize {
         if let Some(utf16_chars) = self.utf16_lines.get(&line) {
             for c in utf16_chars {
-                if col > u32::from(c.start) {
-                    col += u32::from(c.len()) - c.len_utf16() as u32;
-                } else {
+                let start = u32::from(c.start);
+                if col <= start {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account
                     break;
                 }
+
+                let len_encoded = c.len_encoded(encoding) as u32;
+                if col < start + len_encoded {
+                    // The column points into the middle of the character,
+                    // which is not a valid offset. Use the start of the character instead.
+                    col = start;
+                    break;
+                }
+
+                col += u32::from(c.len()) - len_encoded;
             }
         }
 
@@ -215,3 +283,66 @@ where
 
     left
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "a\u{1F600}b\n\u{4E2D}\u{6587}x";
+
+    fn check_roundtrip(encoding: PositionEncoding, utf8_col: u32, encoded_col: u32, line: u32) {
+        let line_index = LineIndex::with_encoding(TEXT, encoding);
+        let line_col = LineCol {
+            line,
+            col: utf8_col,
+        };
+        let encoded = LineColUtf16 {
+            line,
+            col: encoded_col,
+        };
+
+        assert_eq!(line_index.to_encoded(line_col, encoding), encoded);
+        assert_eq!(line_index.to_decoded(encoded, encoding), line_col);
+    }
+
+    #[test]
+    fn test_utf8() {
+        check_roundtrip(PositionEncoding::Utf8, 5, 5, 0);
+        check_roundtrip(PositionEncoding::Utf8, 6, 6, 1);
+    }
+
+    #[test]
+    fn test_utf16() {
+        check_roundtrip(PositionEncoding::Utf16, 5, 3, 0);
+        check_roundtrip(PositionEncoding::Utf16, 6, 2, 1);
+    }
+
+    #[test]
+    fn test_utf32() {
+        check_roundtrip(PositionEncoding::Utf32, 5, 2, 0);
+        check_roundtrip(PositionEncoding::Utf32, 6, 2, 1);
+    }
+
+    #[test]
+    fn test_utf8_inside_character() {
+        let line_index = LineIndex::with_encoding(TEXT, PositionEncoding::Utf8);
+        let line_col =
+            line_index.to_decoded(LineColUtf16 { line: 1, col: 4 }, PositionEncoding::Utf8);
+        assert_eq!(line_col, LineCol { line: 1, col: 3 });
+
+        let offset = line_index.offset(line_col);
+        assert!(TEXT.is_char_boundary(offset.into()));
+
+        let mut text = TEXT.to_string();
+        text.replace_range(usize::from(offset)..usize::from(offset), "y");
+        assert_eq!(text, "a\u{1F600}b\n\u{4E2D}y\u{6587}x");
+    }
+
+    #[test]
+    fn test_utf16_inside_surrogate_pair() {
+        let line_index = LineIndex::with_encoding(TEXT, PositionEncoding::Utf16);
+        let line_col =
+            line_index.to_decoded(LineColUtf16 { line: 0, col: 2 }, PositionEncoding::Utf16);
+        assert_eq!(line_col, LineCol { line: 0, col: 1 });
+    }
+}
@@ -19,7 +19,7 @@ impl LineIndexExt for LineIndex {
             line: line_col.line,
             col: line_col.character,
         };
-        self.offset(self.to_utf8(line_col))
+        self.offset(self.to_decoded(line_col, self.encoding))
     }
 
     fn offset_lsp_range(&self, line_col: Range) -> TextRange {
@@ -30,7 +30,7 @@ impl LineIndexExt for LineIndex {
 
     fn line_col_lsp(&self, offset: TextSize) -> Position {
         let position = self.line_col(offset);
-        let LineColUtf16 { line, col } = self.to_utf16(position);
+        let LineColUtf16 { line, col } = self.to_encoded(position, self.encoding);
         Position::new(line, col)
     }
 
@@ -1,10 +1,27 @@
-use std::{env, fs::OpenOptions, io, path::PathBuf};
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    process,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
 
-use anyhow::Result;
-use log::LevelFilter;
-use lsp_server::Connection;
+use anyhow::{anyhow, bail, Result};
+use crossbeam_channel::bounded;
+use log::{error, info, LevelFilter};
+use lsp_server::{Connection, Message};
+use lsp_types::FormattingOptions;
 use structopt::StructOpt;
-use texlab::Server;
+use texlab::{
+    check_project, create_workspace_headless,
+    distro::Distribution,
+    features::{build_dependency_graph, GraphFormat},
+    format_file, load_project_indent_options, load_project_options, write_formatted_file,
+    CheckFormat, Server, ServerContext,
+};
 
 /// An implementation of the Language Server Protocol for LaTeX
 #[derive(Debug, StructOpt)]
@@ -20,19 +37,366 @@ struct Opts {
     /// Write the logging output to FILE
     #[structopt(long, name = "FILE", parse(from_os_str))]
     log_file: Option<PathBuf>,
+
+    /// Listen for client connections on ADDRESS instead of using stdio.
+    /// Every connection is handled by a separate session.
+    ///
+    /// ADDRESS is either a TCP address (`127.0.0.1:9257`) or a Unix socket (`unix:/tmp/texlab.sock`).
+    #[structopt(long, value_name = "ADDRESS", conflicts_with = "connect")]
+    listen: Option<String>,
+
+    /// Connect to a client listening on ADDRESS instead of using stdio.
+    ///
+    /// ADDRESS is either a TCP address (`127.0.0.1:9257`) or a Unix socket (`unix:/tmp/texlab.sock`).
+    #[structopt(long, value_name = "ADDRESS")]
+    connect: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Run the diagnostics of a project without an editor and exit with a non-zero code on errors
+    Check {
+        /// The root directory of the project
+        #[structopt(parse(from_os_str), default_value = ".")]
+        root: PathBuf,
+
+        /// The output format (text, json or sarif)
+        #[structopt(long, default_value = "text")]
+        format: CheckFormat,
+
+        /// Lint the LaTeX files with chktex
+        #[structopt(long)]
+        chktex: bool,
+    },
+
+    /// Format BibTeX and LaTeX files with the formatters configured in `.texlab.json`
+    Format {
+        /// The files to format
+        #[structopt(parse(from_os_str), required = true)]
+        files: Vec<PathBuf>,
+
+        /// Exit with a non-zero code if a file is not formatted instead of printing the output
+        #[structopt(long, conflicts_with = "in-place")]
+        check: bool,
+
+        /// Overwrite the files with the formatted output
+        #[structopt(short, long)]
+        in_place: bool,
+
+        /// The number of spaces per indentation level (defaults to `tabSize` or 2)
+        #[structopt(long)]
+        tab_size: Option<u32>,
+
+        /// Indent with tabs instead of spaces (defaults to `insertSpaces`)
+        #[structopt(long, conflicts_with = "use-spaces")]
+        use_tabs: bool,
+
+        /// Indent with spaces instead of tabs (defaults to `insertSpaces`)
+        #[structopt(long)]
+        use_spaces: bool,
+    },
+
+    /// Print the include graph of a project
+    Graph {
+        /// The root directory of the project
+        #[structopt(parse(from_os_str), default_value = ".")]
+        root: PathBuf,
+
+        /// The output format (dot or json)
+        #[structopt(long, default_value = "dot")]
+        format: GraphFormat,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Address {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Address {
+    fn parse(text: &str) -> Result<Self> {
+        match text.strip_prefix("unix:") {
+            #[cfg(unix)]
+            Some(path) => Ok(Self::Unix(PathBuf::from(path))),
+            #[cfg(not(unix))]
+            Some(_) => bail!("Unix domain sockets are not supported on this platform"),
+            None => Ok(Self::Tcp(text.to_string())),
+        }
+    }
 }
 
 fn main() -> Result<()> {
-    let opts = Opts::from_args();
+    let mut opts = Opts::from_args();
+    let command = opts.command.take();
+    let listen = opts.listen.as_deref().map(Address::parse).transpose()?;
+    let connect = opts.connect.as_deref().map(Address::parse).transpose()?;
     setup_logger(opts);
 
-    let (connection, threads) = Connection::stdio();
+    if let Some(command) = command {
+        run_command(command)
+    } else if let Some(address) = listen {
+        listen_on(address)
+    } else if let Some(address) = connect {
+        connect_to(address)
+    } else {
+        let (connection, threads) = Connection::stdio();
+        Server::with_connection(connection, env::current_dir()?, true)?.run()?;
+        threads.join()?;
+        Ok(())
+    }
+}
+
+fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Check {
+            root,
+            format,
+            chktex,
+        } => {
+            let root = root.canonicalize()?;
+            let options = load_project_options(&root)?;
+            let report = check_project(&root, options, chktex)?;
+            print!("{}", report.render(format, &root));
+            if report.has_errors() {
+                process::exit(1);
+            }
+        }
+        Command::Format {
+            files,
+            check,
+            in_place,
+            tab_size,
+            use_tabs,
+            use_spaces,
+        } => {
+            if files.len() > 1 && !check && !in_place {
+                bail!("Formatting multiple files requires --check or --in-place");
+            }
+
+            let mut failed = false;
+            for path in files {
+                let directory = path.parent().map(ToOwned::to_owned).unwrap_or_default();
+                let directory = env::current_dir()?.join(directory);
+                let options = load_project_options(&directory)?;
+                let indent_options = load_project_indent_options(&directory)?;
+                let insert_spaces = if use_tabs {
+                    false
+                } else if use_spaces {
+                    true
+                } else {
+                    indent_options.insert_spaces.unwrap_or(true)
+                };
+
+                let formatting_options = FormattingOptions {
+                    tab_size: tab_size.or(indent_options.tab_size).unwrap_or(2),
+                    insert_spaces,
+                    ..FormattingOptions::default()
+                };
+
+                match format_file(&path, options.clone(), formatting_options)? {
+                    Some(file) if check => {
+                        if file.is_changed() {
+                            println!("{}", path.display());
+                            failed = true;
+                        }
+                    }
+                    Some(file) if in_place => {
+                        if file.is_changed() {
+                            if let Err(why) = write_formatted_file(&path, &file.new_text, &options)
+                            {
+                                eprintln!("Unable to write {}: {}", path.display(), why);
+                                failed = true;
+                            }
+                        }
+                    }
+                    Some(file) => print!("{}", file.new_text),
+                    None => {
+                        eprintln!("Unable to format {}", path.display());
+                        failed = true;
+                    }
+                }
+            }
+
+            if failed {
+                process::exit(1);
+            }
+        }
+        Command::Graph { root, format } => {
+            let root = root.canonicalize()?;
+            let options = load_project_options(&root)?;
+            let context = Arc::new(ServerContext::with_folder(root, options));
+            *context.resolver.lock().unwrap() = Distribution::detect().resolver;
+            let workspace = create_workspace_headless(context)?;
+            let graph = build_dependency_graph(&workspace, None);
+            match format {
+                GraphFormat::Dot => print!("{}", graph.to_dot()),
+                GraphFormat::Json => println!("{}", serde_json::to_string_pretty(&graph)?),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn listen_on(address: Address) -> Result<()> {
+    match address {
+        Address::Tcp(address) => {
+            let listener = TcpListener::bind(&address)?;
+            info!("Listening on {}", address);
+            for stream in listener.incoming() {
+                // A failed connection attempt must not stop the server.
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(why) => {
+                        error!("Failed to accept connection: {}", why);
+                        continue;
+                    }
+                };
+
+                match (stream.peer_addr(), stream.try_clone()) {
+                    (Ok(peer_address), Ok(reader)) => {
+                        info!("Accepted connection from {}", peer_address);
+                        spawn_session(reader, stream);
+                    }
+                    (Err(why), _) | (_, Err(why)) => {
+                        error!("Failed to set up connection: {}", why);
+                    }
+                }
+            }
+        }
+        #[cfg(unix)]
+        Address::Unix(path) => {
+            use std::os::unix::{
+                fs::FileTypeExt,
+                net::{UnixListener, UnixStream},
+            };
+
+            // Only a stale socket of a previous server may be replaced.
+            if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+                if !metadata.file_type().is_socket() {
+                    bail!("{} exists and is not a socket", path.display());
+                }
+
+                match UnixStream::connect(&path) {
+                    Ok(_) => bail!("{}: address already in use", path.display()),
+                    Err(why) if why.kind() == io::ErrorKind::ConnectionRefused => {
+                        std::fs::remove_file(&path)?;
+                    }
+                    Err(why) => bail!("Unable to check {}: {}", path.display(), why),
+                }
+            }
+
+            let listener = UnixListener::bind(&path)?;
+            info!("Listening on {}", path.display());
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(why) => {
+                        error!("Failed to accept connection: {}", why);
+                        continue;
+                    }
+                };
+
+                match stream.try_clone() {
+                    Ok(reader) => {
+                        info!("Accepted connection on {}", path.display());
+                        spawn_session(reader, stream);
+                    }
+                    Err(why) => error!("Failed to set up connection: {}", why),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn connect_to(address: Address) -> Result<()> {
+    match address {
+        Address::Tcp(address) => {
+            let stream = TcpStream::connect(&address)?;
+            run_session(stream.try_clone()?, stream)
+        }
+        #[cfg(unix)]
+        Address::Unix(path) => {
+            let stream = std::os::unix::net::UnixStream::connect(&path)?;
+            run_session(stream.try_clone()?, stream)
+        }
+    }
+}
+
+fn spawn_session<R, W>(reader: R, writer: W)
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || match run_session(reader, writer) {
+        Ok(()) => info!("Session closed"),
+        Err(why) => error!("Session failed: {}", why),
+    });
+}
+
+fn run_session<R, W>(reader: R, writer: W) -> Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let (connection, reader_thread, writer_thread) = socket_connection(reader, writer);
     Server::with_connection(connection, env::current_dir()?, true)?.run()?;
-    threads.join()?;
+    reader_thread
+        .join()
+        .map_err(|_| anyhow!("reader thread panicked"))??;
+    writer_thread
+        .join()
+        .map_err(|_| anyhow!("writer thread panicked"))??;
 
     Ok(())
 }
 
+/// Creates a connection that exchanges messages over the given streams
+/// (analogous to `Connection::stdio`).
+fn socket_connection<R, W>(
+    reader: R,
+    writer: W,
+) -> (
+    Connection,
+    JoinHandle<io::Result<()>>,
+    JoinHandle<io::Result<()>>,
+)
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let (reader_sender, receiver) = bounded::<Message>(0);
+    let reader_thread = thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        while let Some(message) = Message::read(&mut reader)? {
+            let is_exit = matches!(&message, Message::Notification(notification) if notification.method == "exit");
+            if reader_sender.send(message).is_err() || is_exit {
+                break;
+            }
+        }
+
+        Ok(())
+    });
+
+    let (sender, writer_receiver) = bounded::<Message>(0);
+    let writer_thread = thread::spawn(move || {
+        let mut writer = BufWriter::new(writer);
+        writer_receiver
+            .into_iter()
+            .try_for_each(|message| message.write(&mut writer))
+    });
+
+    let connection = Connection { sender, receiver };
+    (connection, reader_thread, writer_thread)
+}
+
 fn setup_logger(opts: Opts) {
     let verbosity_level = if !opts.quiet {
         match opts.verbosity {
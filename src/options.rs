@@ -17,6 +17,8 @@ pub struct Options {
 
     pub formatter_line_length: Option<i32>,
 
+    pub default_encoding: Option<String>,
+
     pub diagnostics_delay: Option<u64>,
 
     #[serde(default)]
@@ -28,6 +30,9 @@ pub struct Options {
     #[serde(default)]
     pub latexindent: LatexindentOptions,
 
+    #[serde(default)]
+    pub index: IndexOptions,
+
     pub forward_search: Option<ForwardSearchOptions>,
 }
 
@@ -124,6 +129,13 @@ pub struct ChktexOptions {
     pub on_edit: bool,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexOptions {
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
 pub struct ForwardSearchOptions {
     pub executable: Option<String>,
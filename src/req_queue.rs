@@ -1,9 +1,13 @@
+use std::time::Instant;
+
 use cancellation::CancellationTokenSource;
 use crossbeam_channel::Sender;
 use lsp_server::ResponseError;
 
 pub struct IncomingData {
     pub(crate) token_source: CancellationTokenSource,
+    pub(crate) method: String,
+    pub(crate) start_time: Instant,
 }
 
 pub struct OutgoingData {
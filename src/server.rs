@@ -1,21 +1,33 @@
 use std::{
+    borrow::Cow,
+    convert::TryFrom,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
 };
 
 use anyhow::Result;
 use cancellation::{CancellationToken, CancellationTokenSource};
 use crossbeam_channel::Sender;
+use cstree::{TextRange, TextSize};
 use log::{error, info, warn};
-use lsp_server::{Connection, ErrorCode, Message, RequestId};
+use lsp_server::{Connection, ErrorCode, Message, RequestId, ResponseError};
 use lsp_types::{
     notification::{
-        Cancel, DidChangeConfiguration, DidChangeTextDocument, DidOpenTextDocument,
-        DidSaveTextDocument, PublishDiagnostics,
+        Cancel, DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles,
+        DidChangeWorkspaceFolders, DidOpenTextDocument, DidSaveTextDocument, Notification,
+        Progress, PublishDiagnostics, WorkDoneProgressCancel,
     },
     request::{
-        DocumentLinkRequest, FoldingRangeRequest, Formatting, GotoDefinition, PrepareRenameRequest,
-        References, Rename, SemanticTokensRangeRequest,
+        ApplyWorkspaceEdit, CodeActionRequest, CodeLensRequest, CodeLensResolve,
+        ColorPresentationRequest, DocumentColor, DocumentLinkRequest, ExecuteCommand,
+        FoldingRangeRequest, Formatting, GotoDefinition, LinkedEditingRange, OnTypeFormatting,
+        PrepareRenameRequest, RangeFormatting, References, RegisterCapability, Rename,
+        SelectionRangeRequest, SemanticTokensFullDeltaRequest, SemanticTokensFullRequest,
+        SemanticTokensRangeRequest, SignatureHelpRequest, WorkDoneProgressCreate,
     },
     *,
 };
@@ -24,25 +36,40 @@ use request::{
     Completion, DocumentHighlightRequest, DocumentSymbolRequest, HoverRequest,
     ResolveCompletionItem, WorkspaceSymbol,
 };
-use serde::Serialize;
+use rustc_hash::FxHashSet;
+use serde::{de::DeserializeOwned, Serialize};
 use threadpool::ThreadPool;
+use uuid::Uuid;
 
 use crate::{
-    client::send_notification,
+    client::{send_notification, send_request, send_request_without_waiting},
     component_db::COMPONENT_DATABASE,
     config::{pull_config, push_config, register_config_capability},
+    context::is_inside_folder,
     create_workspace_full,
-    diagnostics::{DiagnosticsDebouncer, DiagnosticsManager, DiagnosticsMessage},
+    diagnostics::{
+        DiagnosticsDebouncer, DiagnosticsManager, DiagnosticsMessage, DocumentDiagnosticParams,
+        DocumentDiagnosticReport, WorkspaceDiagnosticParams, WorkspaceDiagnosticReport,
+    },
     dispatch::{NotificationDispatcher, RequestDispatcher},
     distro::Distribution,
     features::{
-        find_all_references, find_document_highlights, find_document_links, find_document_symbols,
-        find_foldings, find_hover, find_workspace_symbols, format_source_code, goto_definition,
-        prepare_rename_all, rename_all, BuildEngine, BuildParams, BuildResult, BuildStatus,
-        FeatureRequest, ForwardSearchResult,
+        build_dependency_graph, change_environment, clean_auxiliary, find_all_references,
+        find_code_actions, find_code_lenses, find_color_presentations, find_document_colors,
+        find_document_highlights, find_document_links, find_document_symbols, find_foldings,
+        find_hover, find_inlay_hints, find_linked_editing_ranges, find_selection_ranges,
+        find_signature_help, find_workspace_symbols, format_source_code,
+        format_source_code_on_type, format_source_code_range, goto_definition, prepare_rename_all,
+        rename_all, resolve_code_lens, show_dependency_graph, BuildEngine, BuildParams,
+        BuildResult, BuildStatus, ChangeEnvironmentParams, CodeLensData, DependencyGraph,
+        DependencyGraphParams, FeatureRequest, ForwardSearchResult, InlayHint, InlayHintParams,
+        BUILD_COMMAND, CHANGE_ENVIRONMENT_COMMAND, CLEAN_AUXILIARY_COMMAND, COMMANDS,
+        FORWARD_SEARCH_COMMAND, SHOW_DEPENDENCY_GRAPH_COMMAND,
     },
+    find_indexable_files, index_files,
     req_queue::{IncomingData, ReqQueue},
-    DocumentLanguage, ServerContext, Uri, Workspace, WorkspaceSource,
+    ClientCapabilitiesExt, Document, DocumentLanguage, LineIndex, LineIndexExt, PositionEncoding,
+    QueueStatistics, ServerContext, StatisticsReport, Uri, Workspace, WorkspaceSource,
 };
 
 pub struct Server {
@@ -55,6 +82,9 @@ pub struct Server {
     pool: ThreadPool,
     load_resolver: bool,
     build_engine: Arc<BuildEngine>,
+    diag_manager: Arc<Mutex<DiagnosticsManager>>,
+    #[cfg(feature = "semantic")]
+    semantic_tokens: Arc<crate::features::SemanticTokensCache>,
 }
 
 impl Server {
@@ -64,15 +94,23 @@ impl Server {
         load_resolver: bool,
     ) -> Result<Self> {
         let context = Arc::new(ServerContext::new(current_dir));
-        let req_queue = Arc::default();
+        let req_queue: Arc<Mutex<ReqQueue>> = Arc::default();
         let workspace = Arc::new(create_workspace_full(Arc::clone(&context))?);
         let diag_manager = Arc::new(Mutex::new(DiagnosticsManager::default()));
 
-        let static_debouncer =
-            create_static_debouncer(Arc::clone(&diag_manager), &connection, Arc::clone(&context));
+        let static_debouncer = create_static_debouncer(
+            Arc::clone(&diag_manager),
+            &connection,
+            Arc::clone(&req_queue),
+            Arc::clone(&context),
+        );
 
-        let chktex_debouncer =
-            create_chktex_debouncer(diag_manager, &connection, Arc::clone(&context));
+        let chktex_debouncer = create_chktex_debouncer(
+            Arc::clone(&diag_manager),
+            &connection,
+            Arc::clone(&req_queue),
+            Arc::clone(&context),
+        );
 
         Ok(Self {
             connection,
@@ -84,6 +122,9 @@ impl Server {
             pool: threadpool::Builder::new().build(),
             load_resolver,
             build_engine: Arc::default(),
+            diag_manager,
+            #[cfg(feature = "semantic")]
+            semantic_tokens: Arc::default(),
         })
     }
 
@@ -92,7 +133,7 @@ impl Server {
             text_document_sync: Some(TextDocumentSyncCapability::Options(
                 TextDocumentSyncOptions {
                     open_close: Some(true),
-                    change: Some(TextDocumentSyncKind::Full),
+                    change: Some(TextDocumentSyncKind::Incremental),
                     will_save: None,
                     will_save_wait_until: None,
                     save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
@@ -122,6 +163,13 @@ impl Server {
                 ..CompletionOptions::default()
             }),
             document_symbol_provider: Some(OneOf::Left(true)),
+            workspace: Some(WorkspaceServerCapabilities {
+                workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                    supported: Some(true),
+                    change_notifications: Some(OneOf::Left(true)),
+                }),
+                file_operations: None,
+            }),
             workspace_symbol_provider: Some(OneOf::Left(true)),
             rename_provider: Some(OneOf::Right(RenameOptions {
                 prepare_provider: Some(true),
@@ -129,10 +177,35 @@ impl Server {
             })),
             document_highlight_provider: Some(OneOf::Left(true)),
             document_formatting_provider: Some(OneOf::Left(true)),
+            document_range_formatting_provider: Some(OneOf::Left(true)),
+            document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                first_trigger_character: ",".into(),
+                more_trigger_character: Some(vec!["}".into()]),
+            }),
+            signature_help_provider: Some(SignatureHelpOptions {
+                trigger_characters: Some(vec!["{".into()]),
+                retrigger_characters: None,
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
+            selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+            color_provider: Some(ColorProviderCapability::Simple(true)),
+            linked_editing_range_provider: Some(LinkedEditingRangeServerCapabilities::Simple(true)),
+            code_lens_provider: Some(CodeLensOptions {
+                resolve_provider: Some(true),
+            }),
+            code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                resolve_provider: None,
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            })),
+            execute_command_provider: Some(ExecuteCommandOptions {
+                commands: COMMANDS.iter().map(ToString::to_string).collect(),
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
             #[cfg(feature = "semantic")]
             semantic_tokens_provider: Some(
                 SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
-                    full: None,
+                    full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
                     range: Some(true),
                     legend: SemanticTokensLegend {
                         token_types: crate::features::legend::SUPPORTED_TYPES.to_vec(),
@@ -147,9 +220,36 @@ impl Server {
 
     fn initialize(&mut self) -> Result<()> {
         let (id, params) = self.connection.initialize_start()?;
+        {
+            // Pull diagnostics are not supported by `lsp-types` yet.
+            let mut diag_manager = self.diag_manager.lock().unwrap();
+            diag_manager.pull_support = params
+                .pointer("/capabilities/textDocument/diagnostic")
+                .is_some();
+            diag_manager.refresh_support = params
+                .pointer("/capabilities/workspace/diagnostics/refreshSupport")
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false);
+        }
+
+        // Position encodings are not supported by `lsp-types` yet.
+        let position_encoding = negotiate_position_encoding(&params);
+        *self.context.position_encoding.lock().unwrap() = position_encoding;
+
         let params: InitializeParams = serde_json::from_value(params)?;
 
         *self.context.client_capabilities.lock().unwrap() = params.capabilities;
+        *self.context.workspace_folders.write().unwrap() = match params.workspace_folders {
+            Some(folders) => folders
+                .into_iter()
+                .map(|folder| Arc::new(folder.uri.into()))
+                .collect(),
+            None => params
+                .root_uri
+                .into_iter()
+                .map(|uri| Arc::new(uri.into()))
+                .collect(),
+        };
         *self.context.client_info.lock().unwrap() = params.client_info;
 
         let result = InitializeResult {
@@ -159,8 +259,18 @@ impl Server {
                 version: Some(env!("CARGO_PKG_VERSION").to_owned()),
             }),
         };
-        self.connection
-            .initialize_finish(id, serde_json::to_value(result)?)?;
+        let mut result = serde_json::to_value(result)?;
+        // The following capabilities were introduced in LSP 3.17, which is newer than
+        // the version of `lsp-types` that we use. Since `ServerCapabilities` has no field
+        // for them, they are added to the serialized result instead.
+        result["capabilities"]["inlayHintProvider"] = true.into();
+        result["capabilities"]["diagnosticProvider"] = serde_json::json!({
+            "identifier": "texlab",
+            "interFileDependencies": true,
+            "workspaceDiagnostics": true,
+        });
+        result["capabilities"]["positionEncoding"] = position_encoding.as_str().into();
+        self.connection.initialize_finish(id, result)?;
 
         let cx = Arc::clone(&self.context);
         if self.load_resolver {
@@ -176,14 +286,13 @@ impl Server {
         let req_queue = Arc::clone(&self.req_queue);
         let sender = self.connection.sender.clone();
         let context = Arc::clone(&self.context);
+        let workspace = Arc::clone(&self.workspace);
         self.pool.execute(move || {
             register_config_capability(&req_queue, &sender, &context.client_capabilities);
-            pull_config(
-                &req_queue,
-                &sender,
-                &context.options,
-                &context.client_capabilities.lock().unwrap(),
-            );
+            register_file_watchers(&req_queue, &sender, &context);
+            pull_config(&req_queue, &sender, &context);
+            let folders = { context.workspace_folders.read().unwrap().clone() };
+            index_workspace(&req_queue, &sender, workspace.as_ref(), &context, &folders);
         });
         Ok(())
     }
@@ -198,15 +307,48 @@ impl Server {
                 };
                 sender.send(message).unwrap();
             }));
+
+        let diag_manager = Arc::clone(&self.diag_manager);
+        let req_queue = Arc::clone(&self.req_queue);
+        let sender = self.connection.sender.clone();
+        let analyze_sender = self.static_debouncer.sender.clone();
+        self.workspace
+            .register_remove_handler(Arc::new(move |workspace, uri| {
+                let mut manager = diag_manager.lock().unwrap();
+                manager.remove(&uri);
+                if manager.pull_support && manager.refresh_support {
+                    drop(manager);
+                    refresh_diagnostics(&req_queue, &sender);
+                } else if let Err(why) = clear_diagnostics(&sender, &uri)
+                    .and_then(|_| publish_diagnostics(&sender, workspace.as_ref(), &manager))
+                {
+                    warn!("Failed to publish diagnostics: {}", why);
+                }
+
+                // The documents that referred to the removed document
+                // may contain undefined citations or references now.
+                for document in find_dependent_documents(workspace.as_ref(), &uri) {
+                    let message = DiagnosticsMessage::Analyze {
+                        workspace: Arc::clone(&workspace),
+                        document,
+                    };
+                    analyze_sender.send(message).unwrap();
+                }
+            }));
     }
 
-    fn register_incoming_request(&self, id: RequestId) -> Arc<CancellationToken> {
+    fn register_incoming_request(&self, id: RequestId, method: String) -> Arc<CancellationToken> {
         let token_source = CancellationTokenSource::new();
         let token = Arc::clone(token_source.token());
         let mut req_queue = self.req_queue.lock().unwrap();
-        req_queue
-            .incoming
-            .register(id.clone(), IncomingData { token_source });
+        req_queue.incoming.register(
+            id.clone(),
+            IncomingData {
+                token_source,
+                method,
+                start_time: Instant::now(),
+            },
+        );
         token
     }
 
@@ -224,8 +366,110 @@ impl Server {
         Ok(())
     }
 
+    fn cancel_progress(&self, params: WorkDoneProgressCancelParams) -> Result<()> {
+        if let NumberOrString::String(token) = params.token {
+            self.build_engine.cancel(&token);
+        }
+
+        Ok(())
+    }
+
     fn did_change_configuration(&self, params: DidChangeConfigurationParams) -> Result<()> {
-        push_config(&self.context.options, params.settings);
+        let diagnostics_sender = self.static_debouncer.sender.clone();
+        let workspace = Arc::clone(&self.workspace);
+        if self
+            .context
+            .client_capabilities
+            .lock()
+            .unwrap()
+            .has_pull_configuration_support()
+        {
+            self.context.config_outdated.store(true, Ordering::SeqCst);
+            let req_queue = Arc::clone(&self.req_queue);
+            let sender = self.connection.sender.clone();
+            let context = Arc::clone(&self.context);
+            self.pool.execute(move || {
+                pull_config(&req_queue, &sender, &context);
+                reanalyze_documents(workspace, &diagnostics_sender);
+            });
+        } else {
+            push_config(&self.context, params.settings);
+            reanalyze_documents(workspace, &diagnostics_sender);
+        }
+        Ok(())
+    }
+
+    fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) -> Result<()> {
+        let removed: Vec<Uri> = params
+            .event
+            .removed
+            .into_iter()
+            .map(|folder| folder.uri.into())
+            .collect();
+
+        let mut added = Vec::new();
+        let remaining = {
+            let mut workspace_folders = self.context.workspace_folders.write().unwrap();
+            workspace_folders.retain(|folder| !removed.contains(folder));
+            self.context
+                .folder_options
+                .write()
+                .unwrap()
+                .retain(|folder, _| !removed.contains(folder));
+
+            for folder in params.event.added {
+                let uri = Arc::new(folder.uri.into());
+                if !workspace_folders.contains(&uri) {
+                    workspace_folders.push(Arc::clone(&uri));
+                    added.push(uri);
+                }
+            }
+
+            self.context.config_outdated.store(true, Ordering::SeqCst);
+            workspace_folders.clone()
+        };
+
+        let req_queue = Arc::clone(&self.req_queue);
+        let sender = self.connection.sender.clone();
+        let context = Arc::clone(&self.context);
+        let workspace = Arc::clone(&self.workspace);
+        self.pool.execute(move || {
+            for document in workspace.documents() {
+                let contains_document = |folder: &Uri| is_inside_folder(folder, &document.uri);
+                if removed.iter().any(contains_document)
+                    && !remaining.iter().any(|folder| contains_document(folder))
+                {
+                    workspace.remove(&document.uri);
+                }
+            }
+
+            pull_config(&req_queue, &sender, &context);
+            index_workspace(&req_queue, &sender, workspace.as_ref(), &context, &added);
+        });
+        Ok(())
+    }
+
+    fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) -> Result<()> {
+        for event in params.changes {
+            let path = match event.uri.to_file_path() {
+                Ok(path) => path,
+                Err(()) => continue,
+            };
+
+            match event.typ {
+                FileChangeType::Created | FileChangeType::Changed => {
+                    if DocumentLanguage::by_path(&path).is_some() {
+                        if let Err(why) = self.workspace.reload(path) {
+                            warn!("Failed to reload {}: {}", event.uri, why);
+                        }
+                    }
+                }
+                FileChangeType::Deleted => {
+                    self.workspace.remove(&event.uri.into());
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -239,7 +483,11 @@ impl Server {
             WorkspaceSource::Client,
         );
 
-        let should_lint = { self.context.options.read().unwrap().chktex.on_open_and_save };
+        let should_lint = self
+            .context
+            .options_for(&document.uri)
+            .chktex
+            .on_open_and_save;
         if let Some(document) = self
             .workspace
             .get(document.uri.as_ref())
@@ -255,38 +503,56 @@ impl Server {
         Ok(())
     }
 
-    fn did_change(&self, mut params: DidChangeTextDocumentParams) -> Result<()> {
-        let uri = params.text_document.uri.into();
-        assert_eq!(params.content_changes.len(), 1);
-        let old_document = self.workspace.get(&uri);
-        let old_text = old_document.as_ref().map(|document| document.text.as_str());
-        let new_text = params.content_changes.pop().unwrap().text;
+    fn did_change(&self, params: DidChangeTextDocumentParams) -> Result<()> {
+        let uri = Arc::new(Uri::from(params.text_document.uri));
+        let old_document = match self.workspace.get(&uri) {
+            Some(document) => document,
+            None => {
+                warn!("Received changes for unknown document: {}", uri);
+                return Ok(());
+            }
+        };
 
-        let uri = Arc::new(uri);
+        let line = params
+            .content_changes
+            .iter()
+            .rev()
+            .find_map(|change| change.range)
+            .map(|range| range.start.line);
+
+        let (new_text, edit) = apply_document_changes(
+            &old_document.text,
+            &old_document.line_index,
+            params.content_changes,
+        );
 
-        let line = match old_text {
-            Some(old_text) => old_text
+        let line = line.unwrap_or_else(|| {
+            old_document
+                .text
                 .lines()
                 .zip(new_text.lines())
                 .position(|(a, b)| a != b)
-                .unwrap_or_default() as u32,
-            None => 0,
-        };
+                .unwrap_or_default() as u32
+        });
         self.build_engine
             .positions_by_uri
             .insert(Arc::clone(&uri), Position::new(line, 0));
 
-        let language = self
-            .workspace
-            .get(&uri)
-            .map(|document| document.data.language())
-            .unwrap_or(DocumentLanguage::Latex);
+        let language = old_document.data.language();
+        let document = match edit {
+            Some(edit) => self.workspace.edit(uri, new_text, edit),
+            None => Some(
+                self.workspace
+                    .open(uri, new_text, language, WorkspaceSource::Client),
+            ),
+        };
 
-        let document = self
-            .workspace
-            .open(uri, new_text, language, WorkspaceSource::Client);
+        let document = match document {
+            Some(document) => document,
+            None => return Ok(()),
+        };
 
-        let should_lint = { self.context.options.read().unwrap().chktex.on_edit };
+        let should_lint = self.context.options_for(&document.uri).chktex.on_edit;
         if let Some(document) = self
             .workspace
             .get(document.uri.as_ref())
@@ -306,7 +572,8 @@ impl Server {
     fn did_save(&self, params: DidSaveTextDocumentParams) -> Result<()> {
         let uri = params.text_document.uri.into();
 
-        let should_build = { self.context.options.read().unwrap().build.on_save };
+        let options = self.context.options_for(&uri);
+        let should_build = options.build.on_save;
         if let Some(request) =
             self.workspace
                 .get(&uri)
@@ -335,7 +602,7 @@ impl Server {
             });
         }
 
-        let should_lint = { self.context.options.read().unwrap().chktex.on_open_and_save };
+        let should_lint = options.chktex.on_open_and_save;
         if let Some(document) = self.workspace.get(&uri).filter(|_| should_lint) {
             self.chktex_debouncer
                 .sender
@@ -350,17 +617,20 @@ impl Server {
     fn did_close(&self, params: DidCloseTextDocumentParams) -> Result<()> {
         let uri = params.text_document.uri.into();
         self.workspace.close(&uri);
+        #[cfg(feature = "semantic")]
+        self.semantic_tokens.remove(&uri);
         Ok(())
     }
 
     fn feature_request<P>(&self, uri: Arc<Uri>, params: P) -> Option<FeatureRequest<P>> {
-        let req_queue = Arc::clone(&self.req_queue);
-        let sender = self.connection.sender.clone();
-        let cx = Arc::clone(&self.context);
-        self.pool.execute(move || {
-            let client_capabilities = &cx.client_capabilities.lock().unwrap().clone();
-            pull_config(&req_queue, &sender, &cx.options, &client_capabilities);
-        });
+        if self.context.config_outdated.load(Ordering::SeqCst) {
+            let req_queue = Arc::clone(&self.req_queue);
+            let sender = self.connection.sender.clone();
+            let cx = Arc::clone(&self.context);
+            self.pool.execute(move || {
+                pull_config(&req_queue, &sender, &cx);
+            });
+        }
 
         Some(FeatureRequest {
             context: Arc::clone(&self.context),
@@ -376,7 +646,13 @@ impl Server {
             ErrorCode::InternalError as i32,
             "unknown document URI".to_string(),
         );
-        self.connection.sender.send(resp.into())?;
+        self.send_response(resp)
+    }
+
+    /// Sends a response that is created on the main loop.
+    fn send_response(&self, response: lsp_server::Response) -> Result<()> {
+        complete_request(&self.req_queue, &self.context, &response.id);
+        self.connection.sender.send(response.into())?;
         Ok(())
     }
 
@@ -392,20 +668,48 @@ impl Server {
         P: Send + 'static,
         R: Serialize,
         H: FnOnce(FeatureRequest<P>, &CancellationToken) -> R + Send + 'static,
+    {
+        self.handle_fallible_feature_request(id, params, uri, token, move |request, token| {
+            Ok(handler(request, token))
+        })
+    }
+
+    /// Like `handle_feature_request` but sends the error of the handler to the client.
+    fn handle_fallible_feature_request<P, R, H>(
+        &self,
+        id: RequestId,
+        params: P,
+        uri: Arc<Uri>,
+        token: &Arc<CancellationToken>,
+        handler: H,
+    ) -> Result<()>
+    where
+        P: Send + 'static,
+        R: Serialize,
+        H: FnOnce(FeatureRequest<P>, &CancellationToken) -> Result<R, ResponseError>
+            + Send
+            + 'static,
     {
         match self.feature_request(uri, params) {
             Some(req) => {
                 let sender = self.connection.sender.clone();
+                let req_queue = Arc::clone(&self.req_queue);
+                let context = Arc::clone(&self.context);
                 let token = Arc::clone(token);
                 self.pool.execute(move || {
                     let result = handler(req, &token);
-                    if token.is_canceled() {
-                        sender.send(cancel_response(id).into()).unwrap();
+                    complete_request(&req_queue, &context, &id);
+                    let response = if token.is_canceled() {
+                        cancel_response(id)
                     } else {
-                        sender
-                            .send(lsp_server::Response::new_ok(id, result).into())
-                            .unwrap();
-                    }
+                        match result {
+                            Ok(result) => lsp_server::Response::new_ok(id, result),
+                            Err(error) => {
+                                lsp_server::Response::new_err(id, error.code, error.message)
+                            }
+                        }
+                    };
+                    sender.send(response.into()).unwrap();
                 });
             }
             None => {
@@ -415,6 +719,34 @@ impl Server {
         Ok(())
     }
 
+    /// Runs a request that is not bound to a single document on the thread pool.
+    fn handle_workspace_request<R, H>(
+        &self,
+        id: RequestId,
+        token: &Arc<CancellationToken>,
+        handler: H,
+    ) -> Result<()>
+    where
+        R: Serialize,
+        H: FnOnce(&CancellationToken) -> R + Send + 'static,
+    {
+        let sender = self.connection.sender.clone();
+        let req_queue = Arc::clone(&self.req_queue);
+        let context = Arc::clone(&self.context);
+        let token = Arc::clone(token);
+        self.pool.execute(move || {
+            let result = handler(&token);
+            complete_request(&req_queue, &context, &id);
+            let response = if token.is_canceled() {
+                cancel_response(id)
+            } else {
+                lsp_server::Response::new_ok(id, result)
+            };
+            sender.send(response.into()).unwrap();
+        });
+        Ok(())
+    }
+
     fn document_link(
         &self,
         id: RequestId,
@@ -443,22 +775,55 @@ impl Server {
         params: WorkspaceSymbolParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
-        let sender = self.connection.sender.clone();
         let workspace = Arc::clone(&self.workspace);
-        let token = Arc::clone(token);
-        self.pool.execute(move || {
-            let result = find_workspace_symbols(workspace.as_ref(), &params, &token);
-            if token.is_canceled() {
-                sender.send(cancel_response(id).into()).unwrap();
-            } else {
-                sender
-                    .send(lsp_server::Response::new_ok(id, result).into())
-                    .unwrap();
-            }
-        });
+        self.handle_workspace_request(id, token, move |token| {
+            find_workspace_symbols(workspace.as_ref(), &params, token)
+        })
+    }
+
+    fn document_diagnostic(
+        &self,
+        id: RequestId,
+        params: DocumentDiagnosticParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        let diag_manager = Arc::clone(&self.diag_manager);
+        self.handle_feature_request(id, params, uri, token, move |request, _| {
+            diag_manager.lock().unwrap().pull_document(
+                request.workspace.as_ref(),
+                Arc::clone(&request.main_document().uri),
+                request.params.previous_result_id.as_deref(),
+            )
+        })?;
         Ok(())
     }
 
+    fn workspace_diagnostic(
+        &self,
+        id: RequestId,
+        params: WorkspaceDiagnosticParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let workspace = Arc::clone(&self.workspace);
+        let diag_manager = Arc::clone(&self.diag_manager);
+        self.handle_workspace_request(id, token, move |_| {
+            let previous_result_ids = params
+                .previous_result_ids
+                .into_iter()
+                .map(|previous| (previous.uri.into(), previous.value))
+                .collect();
+
+            let items = DiagnosticsManager::pull_workspace(
+                &diag_manager,
+                workspace.as_ref(),
+                &previous_result_ids,
+            );
+
+            WorkspaceDiagnosticReport { items }
+        })
+    }
+
     #[cfg(feature = "completion")]
     fn completion(
         &self,
@@ -490,10 +855,8 @@ impl Server {
         mut item: CompletionItem,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
-        let sender = self.connection.sender.clone();
-        let token = Arc::clone(token);
         let workspace = Arc::clone(&self.workspace);
-        self.pool.execute(move || {
+        self.handle_workspace_request(id, token, move |_| {
             match serde_json::from_value(item.data.clone().unwrap()).unwrap() {
                 crate::features::CompletionItemData::Package
                 | crate::features::CompletionItemData::Class => {
@@ -514,15 +877,8 @@ impl Server {
             };
 
             drop(workspace);
-            if token.is_canceled() {
-                sender.send(cancel_response(id).into()).unwrap();
-            } else {
-                sender
-                    .send(lsp_server::Response::new_ok(id, item).into())
-                    .unwrap();
-            }
-        });
-        Ok(())
+            item
+        })
     }
 
     fn folding_range(
@@ -536,98 +892,72 @@ impl Server {
         Ok(())
     }
 
-    fn references(
+    fn signature_help(
         &self,
         id: RequestId,
-        params: ReferenceParams,
+        params: SignatureHelpParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
         let uri = Arc::new(
             params
-                .text_document_position
+                .text_document_position_params
                 .text_document
                 .uri
                 .clone()
                 .into(),
         );
-        self.handle_feature_request(id, params, uri, token, find_all_references)?;
+        self.handle_feature_request(id, params, uri, token, find_signature_help)?;
         Ok(())
     }
 
-    fn hover(
+    fn inlay_hint(
         &self,
         id: RequestId,
-        params: HoverParams,
+        params: InlayHintParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
-        let uri = Arc::new(
-            params
-                .text_document_position_params
-                .text_document
-                .uri
-                .clone()
-                .into(),
-        );
-        self.build_engine.positions_by_uri.insert(
-            Arc::clone(&uri),
-            params.text_document_position_params.position,
-        );
-
-        self.handle_feature_request(id, params, uri, token, find_hover)?;
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(id, params, uri, token, find_inlay_hints)?;
         Ok(())
     }
 
-    fn goto_definition(
+    fn selection_range(
         &self,
         id: RequestId,
-        params: GotoDefinitionParams,
+        params: SelectionRangeParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
-        let uri = Arc::new(
-            params
-                .text_document_position_params
-                .text_document
-                .uri
-                .clone()
-                .into(),
-        );
-        self.handle_feature_request(id, params, uri, token, goto_definition)?;
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(id, params, uri, token, find_selection_ranges)?;
         Ok(())
     }
 
-    fn prepare_rename(
+    fn document_color(
         &self,
         id: RequestId,
-        params: TextDocumentPositionParams,
+        params: DocumentColorParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
         let uri = Arc::new(params.text_document.uri.clone().into());
-        self.handle_feature_request(id, params, uri, token, prepare_rename_all)?;
+        self.handle_feature_request(id, params, uri, token, find_document_colors)?;
         Ok(())
     }
 
-    fn rename(
+    fn color_presentation(
         &self,
         id: RequestId,
-        params: RenameParams,
+        params: ColorPresentationParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
-        let uri = Arc::new(
-            params
-                .text_document_position
-                .text_document
-                .uri
-                .clone()
-                .into(),
-        );
-        self.handle_feature_request(id, params, uri, token, rename_all)?;
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(id, params, uri, token, find_color_presentations)?;
         Ok(())
     }
 
-    fn document_highlight(
+    fn linked_editing_range(
         &self,
         id: RequestId,
-        params: DocumentHighlightParams,
+        params: LinkedEditingRangeParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
         let uri = Arc::new(
@@ -638,62 +968,295 @@ impl Server {
                 .clone()
                 .into(),
         );
-        self.handle_feature_request(id, params, uri, token, find_document_highlights)?;
+        self.handle_feature_request(id, params, uri, token, find_linked_editing_ranges)?;
         Ok(())
     }
 
-    fn formatting(
+    fn code_lens(
         &self,
         id: RequestId,
-        params: DocumentFormattingParams,
+        params: CodeLensParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
         let uri = Arc::new(params.text_document.uri.clone().into());
-        self.handle_feature_request(id, params, uri, token, format_source_code)?;
+        self.handle_feature_request(id, params, uri, token, find_code_lenses)?;
         Ok(())
     }
 
-    #[cfg(feature = "semantic")]
-    fn semantic_tokens_range(
+    fn code_lens_resolve(
         &self,
         id: RequestId,
-        params: SemanticTokensRangeParams,
+        code_lens: CodeLens,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
-        let uri = Arc::new(params.text_document.uri.clone().into());
-        self.handle_feature_request(
-            id,
-            params,
-            uri,
-            token,
-            crate::features::find_semantic_tokens_range,
-        )?;
+        match code_lens
+            .data
+            .clone()
+            .map(serde_json::from_value::<CodeLensData>)
+        {
+            Some(Ok(data)) => {
+                let uri = Arc::new(data.uri);
+                self.handle_feature_request(id, code_lens, uri, token, resolve_code_lens)?;
+            }
+            _ => {
+                self.send_feature_error(id)?;
+            }
+        };
         Ok(())
     }
 
-    #[cfg(not(feature = "semantic"))]
-    fn semantic_tokens_range(
+    fn code_action(
         &self,
-        _id: RequestId,
-        _params: SemanticTokensRangeParams,
-        _token: &Arc<CancellationToken>,
+        id: RequestId,
+        params: CodeActionParams,
+        token: &Arc<CancellationToken>,
     ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(id, params, uri, token, find_code_actions)?;
         Ok(())
     }
 
-    fn build(
+    fn references(
         &self,
         id: RequestId,
-        params: BuildParams,
+        params: ReferenceParams,
         token: &Arc<CancellationToken>,
     ) -> Result<()> {
-        let uri = Arc::new(params.text_document.uri.clone().into());
-        let lsp_sender = self.connection.sender.clone();
-        let req_queue = Arc::clone(&self.req_queue);
-        let build_engine = Arc::clone(&self.build_engine);
-        self.handle_feature_request(id, params, uri, token, move |request, token| {
+        let uri = Arc::new(
+            params
+                .text_document_position
+                .text_document
+                .uri
+                .clone()
+                .into(),
+        );
+        self.handle_feature_request(id, params, uri, token, find_all_references)?;
+        Ok(())
+    }
+
+    fn hover(
+        &self,
+        id: RequestId,
+        params: HoverParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(
+            params
+                .text_document_position_params
+                .text_document
+                .uri
+                .clone()
+                .into(),
+        );
+        self.build_engine.positions_by_uri.insert(
+            Arc::clone(&uri),
+            params.text_document_position_params.position,
+        );
+
+        self.handle_feature_request(id, params, uri, token, find_hover)?;
+        Ok(())
+    }
+
+    fn goto_definition(
+        &self,
+        id: RequestId,
+        params: GotoDefinitionParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(
+            params
+                .text_document_position_params
+                .text_document
+                .uri
+                .clone()
+                .into(),
+        );
+        self.handle_feature_request(id, params, uri, token, goto_definition)?;
+        Ok(())
+    }
+
+    fn prepare_rename(
+        &self,
+        id: RequestId,
+        params: TextDocumentPositionParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(id, params, uri, token, prepare_rename_all)?;
+        Ok(())
+    }
+
+    fn rename(
+        &self,
+        id: RequestId,
+        params: RenameParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(
+            params
+                .text_document_position
+                .text_document
+                .uri
+                .clone()
+                .into(),
+        );
+        self.handle_feature_request(id, params, uri, token, rename_all)?;
+        Ok(())
+    }
+
+    fn document_highlight(
+        &self,
+        id: RequestId,
+        params: DocumentHighlightParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(
+            params
+                .text_document_position_params
+                .text_document
+                .uri
+                .clone()
+                .into(),
+        );
+        self.handle_feature_request(id, params, uri, token, find_document_highlights)?;
+        Ok(())
+    }
+
+    fn formatting(
+        &self,
+        id: RequestId,
+        params: DocumentFormattingParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(id, params, uri, token, format_source_code)?;
+        Ok(())
+    }
+
+    fn range_formatting(
+        &self,
+        id: RequestId,
+        params: DocumentRangeFormattingParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(id, params, uri, token, format_source_code_range)?;
+        Ok(())
+    }
+
+    fn on_type_formatting(
+        &self,
+        id: RequestId,
+        params: DocumentOnTypeFormattingParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(
+            params
+                .text_document_position
+                .text_document
+                .uri
+                .clone()
+                .into(),
+        );
+        self.handle_feature_request(id, params, uri, token, format_source_code_on_type)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "semantic")]
+    fn semantic_tokens_range(
+        &self,
+        id: RequestId,
+        params: SemanticTokensRangeParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        self.handle_feature_request(
+            id,
+            params,
+            uri,
+            token,
+            crate::features::find_semantic_tokens_range,
+        )?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "semantic"))]
+    fn semantic_tokens_range(
+        &self,
+        _id: RequestId,
+        _params: SemanticTokensRangeParams,
+        _token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(feature = "semantic")]
+    fn semantic_tokens_full(
+        &self,
+        id: RequestId,
+        params: SemanticTokensParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        let cache = Arc::clone(&self.semantic_tokens);
+        self.handle_feature_request(id, params, uri, token, move |request, token| {
+            crate::features::find_semantic_tokens_full(request, &cache, token)
+        })?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "semantic"))]
+    fn semantic_tokens_full(
+        &self,
+        _id: RequestId,
+        _params: SemanticTokensParams,
+        _token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(feature = "semantic")]
+    fn semantic_tokens_full_delta(
+        &self,
+        id: RequestId,
+        params: SemanticTokensDeltaParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        let cache = Arc::clone(&self.semantic_tokens);
+        self.handle_feature_request(id, params, uri, token, move |request, token| {
+            crate::features::find_semantic_tokens_full_delta(request, &cache, token)
+        })?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "semantic"))]
+    fn semantic_tokens_full_delta(
+        &self,
+        _id: RequestId,
+        _params: SemanticTokensDeltaParams,
+        _token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn build(
+        &self,
+        id: RequestId,
+        params: BuildParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let uri = Arc::new(params.text_document.uri.clone().into());
+        let lsp_sender = self.connection.sender.clone();
+        let req_queue = Arc::clone(&self.req_queue);
+        let build_engine = Arc::clone(&self.build_engine);
+        // A cancelled build reports its status instead of a cancellation error,
+        // so the response does not depend on the token of the request.
+        let build_token = Arc::clone(token);
+        let response_token = Arc::clone(CancellationTokenSource::new().token());
+        self.handle_feature_request(id, params, uri, &response_token, move |request, _| {
             build_engine
-                .build(request, token, &req_queue, &lsp_sender)
+                .build(request, &build_token, &req_queue, &lsp_sender)
                 .unwrap_or_else(|why| {
                     error!("Build failed: {}", why);
                     BuildResult {
@@ -704,6 +1267,36 @@ impl Server {
         Ok(())
     }
 
+    fn statistics(&self, id: RequestId, token: &Arc<CancellationToken>) -> Result<()> {
+        // The queues are measured before the report itself is queued.
+        let queues = QueueStatistics {
+            diagnostics: self.static_debouncer.sender.len(),
+            chktex: self.chktex_debouncer.sender.len(),
+            pending_tasks: self.pool.queued_count(),
+        };
+
+        let context = Arc::clone(&self.context);
+        let workspace = Arc::clone(&self.workspace);
+        self.handle_workspace_request(id, token, move |_| {
+            context.statistics.report(workspace.as_ref(), queues)
+        })
+    }
+
+    fn dependency_graph(
+        &self,
+        id: RequestId,
+        params: DependencyGraphParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        let workspace = Arc::clone(&self.workspace);
+        self.handle_workspace_request(id, token, move |_| {
+            let uri = params
+                .text_document
+                .map(|text_document| Arc::new(text_document.uri.into()));
+            build_dependency_graph(workspace.as_ref(), uri)
+        })
+    }
+
     fn forward_search(
         &self,
         id: RequestId,
@@ -721,6 +1314,116 @@ impl Server {
         Ok(())
     }
 
+    fn execute_command(
+        &self,
+        id: RequestId,
+        params: ExecuteCommandParams,
+        token: &Arc<CancellationToken>,
+    ) -> Result<()> {
+        match params.command.as_str() {
+            BUILD_COMMAND => match parse_command_argument::<TextDocumentIdentifier>(params) {
+                Some(text_document) => self.build(id, BuildParams { text_document }, token)?,
+                None => self.send_invalid_command_error(id)?,
+            },
+            FORWARD_SEARCH_COMMAND => {
+                match parse_command_argument::<TextDocumentPositionParams>(params) {
+                    Some(params) => self.forward_search(id, params, token)?,
+                    None => self.send_invalid_command_error(id)?,
+                }
+            }
+            CLEAN_AUXILIARY_COMMAND => {
+                match parse_command_argument::<TextDocumentIdentifier>(params) {
+                    Some(params) => {
+                        let uri = Arc::new(params.uri.clone().into());
+                        self.handle_fallible_feature_request(
+                            id,
+                            params,
+                            uri,
+                            token,
+                            |request, token| {
+                                clean_auxiliary(request, token).map_err(|why| {
+                                    error!("Failed to clean auxiliary files: {}", why);
+                                    ResponseError {
+                                        code: ErrorCode::InternalError as i32,
+                                        message: format!(
+                                            "Failed to clean auxiliary files: {}",
+                                            why
+                                        ),
+                                        data: None,
+                                    }
+                                })
+                            },
+                        )?;
+                    }
+                    None => self.send_invalid_command_error(id)?,
+                }
+            }
+            CHANGE_ENVIRONMENT_COMMAND => {
+                match parse_command_argument::<ChangeEnvironmentParams>(params) {
+                    Some(params) => {
+                        let uri = Arc::new(
+                            params
+                                .text_document_position
+                                .text_document
+                                .uri
+                                .clone()
+                                .into(),
+                        );
+                        let lsp_sender = self.connection.sender.clone();
+                        let req_queue = Arc::clone(&self.req_queue);
+                        self.handle_feature_request(
+                            id,
+                            params,
+                            uri,
+                            token,
+                            move |request, token| {
+                                if let Some(edit) = change_environment(request, token) {
+                                    let params = ApplyWorkspaceEditParams {
+                                        label: Some("Change environment".into()),
+                                        edit,
+                                    };
+
+                                    if let Err(why) = send_request_without_waiting::<
+                                        ApplyWorkspaceEdit,
+                                    >(
+                                        &req_queue, &lsp_sender, params
+                                    ) {
+                                        error!("Failed to change environment: {}", why);
+                                    }
+                                }
+                            },
+                        )?;
+                    }
+                    None => self.send_invalid_command_error(id)?,
+                }
+            }
+            SHOW_DEPENDENCY_GRAPH_COMMAND => {
+                let workspace = Arc::clone(&self.workspace);
+                self.handle_workspace_request(id, token, move |_| {
+                    show_dependency_graph(workspace.as_ref())
+                })?;
+            }
+            _ => {
+                let resp = lsp_server::Response::new_err(
+                    id,
+                    ErrorCode::InvalidParams as i32,
+                    format!("unknown command: {}", params.command),
+                );
+                self.send_response(resp)?;
+            }
+        };
+        Ok(())
+    }
+
+    fn send_invalid_command_error(&self, id: RequestId) -> Result<()> {
+        let resp = lsp_server::Response::new_err(
+            id,
+            ErrorCode::InvalidParams as i32,
+            "invalid command arguments".to_string(),
+        );
+        self.send_response(resp)
+    }
+
     fn process_messages(&self) -> Result<()> {
         for msg in &self.connection.receiver {
             match msg {
@@ -729,7 +1432,8 @@ impl Server {
                         return Ok(());
                     }
 
-                    let token = self.register_incoming_request(request.id.clone());
+                    let token =
+                        self.register_incoming_request(request.id.clone(), request.method.clone());
                     if let Some(response) = RequestDispatcher::new(request)
                         .on::<DocumentLinkRequest, _>(|id, params| {
                             self.document_link(id, params, &token)
@@ -766,24 +1470,81 @@ impl Server {
                             self.document_highlight(id, params, &token)
                         })?
                         .on::<Formatting, _>(|id, params| self.formatting(id, params, &token))?
+                        .on::<RangeFormatting, _>(|id, params| {
+                            self.range_formatting(id, params, &token)
+                        })?
+                        .on::<OnTypeFormatting, _>(|id, params| {
+                            self.on_type_formatting(id, params, &token)
+                        })?
+                        .on::<SignatureHelpRequest, _>(|id, params| {
+                            self.signature_help(id, params, &token)
+                        })?
+                        .on::<InlayHintRequest, _>(|id, params| {
+                            self.inlay_hint(id, params, &token)
+                        })?
+                        .on::<DocumentDiagnosticRequest, _>(|id, params| {
+                            self.document_diagnostic(id, params, &token)
+                        })?
+                        .on::<WorkspaceDiagnosticRequest, _>(|id, params| {
+                            self.workspace_diagnostic(id, params, &token)
+                        })?
+                        .on::<SelectionRangeRequest, _>(|id, params| {
+                            self.selection_range(id, params, &token)
+                        })?
+                        .on::<DocumentColor, _>(|id, params| {
+                            self.document_color(id, params, &token)
+                        })?
+                        .on::<ColorPresentationRequest, _>(|id, params| {
+                            self.color_presentation(id, params, &token)
+                        })?
+                        .on::<LinkedEditingRange, _>(|id, params| {
+                            self.linked_editing_range(id, params, &token)
+                        })?
+                        .on::<CodeLensRequest, _>(|id, params| self.code_lens(id, params, &token))?
+                        .on::<CodeLensResolve, _>(|id, params| {
+                            self.code_lens_resolve(id, params, &token)
+                        })?
+                        .on::<CodeActionRequest, _>(|id, params| {
+                            self.code_action(id, params, &token)
+                        })?
+                        .on::<ExecuteCommand, _>(|id, params| {
+                            self.execute_command(id, params, &token)
+                        })?
                         .on::<BuildRequest, _>(|id, params| self.build(id, params, &token))?
                         .on::<ForwardSearchRequest, _>(|id, params| {
                             self.forward_search(id, params, &token)
                         })?
+                        .on::<DependencyGraphRequest, _>(|id, params| {
+                            self.dependency_graph(id, params, &token)
+                        })?
+                        .on::<StatisticsRequest, _>(|id, ()| self.statistics(id, &token))?
                         .on::<SemanticTokensRangeRequest, _>(|id, params| {
                             self.semantic_tokens_range(id, params, &token)
                         })?
+                        .on::<SemanticTokensFullRequest, _>(|id, params| {
+                            self.semantic_tokens_full(id, params, &token)
+                        })?
+                        .on::<SemanticTokensFullDeltaRequest, _>(|id, params| {
+                            self.semantic_tokens_full_delta(id, params, &token)
+                        })?
                         .default()
                     {
-                        self.connection.sender.send(response.into())?;
+                        self.send_response(response)?;
                     }
                 }
                 Message::Notification(notification) => {
                     NotificationDispatcher::new(notification)
                         .on::<Cancel, _>(|params| self.cancel(params))?
+                        .on::<WorkDoneProgressCancel, _>(|params| self.cancel_progress(params))?
                         .on::<DidChangeConfiguration, _>(|params| {
                             self.did_change_configuration(params)
                         })?
+                        .on::<DidChangeWorkspaceFolders, _>(|params| {
+                            self.did_change_workspace_folders(params)
+                        })?
+                        .on::<DidChangeWatchedFiles, _>(|params| {
+                            self.did_change_watched_files(params)
+                        })?
                         .on::<DidOpenTextDocument, _>(|params| self.did_open(params))?
                         .on::<DidChangeTextDocument, _>(|params| self.did_change(params))?
                         .on::<DidSaveTextDocument, _>(|params| self.did_save(params))?
@@ -797,7 +1558,8 @@ impl Server {
                         Some(error) => Err(error),
                         None => Ok(response.result.unwrap_or_default()),
                     };
-                    data.sender.send(result)?;
+                    // Nobody listens if the request has been sent without waiting.
+                    let _ = data.sender.send(result);
                 }
             }
         }
@@ -817,13 +1579,17 @@ impl Server {
 fn create_static_debouncer(
     manager: Arc<Mutex<DiagnosticsManager>>,
     conn: &Connection,
+    req_queue: Arc<Mutex<ReqQueue>>,
     context: Arc<ServerContext>,
 ) -> DiagnosticsDebouncer {
     let sender = conn.sender.clone();
     DiagnosticsDebouncer::launch(context, move |workspace, document| {
         let mut manager = manager.lock().unwrap();
         manager.update_static(workspace.as_ref(), Arc::clone(&document.uri));
-        if let Err(why) = publish_diagnostics(&sender, workspace.as_ref(), &manager) {
+        if manager.pull_support && manager.refresh_support {
+            drop(manager);
+            refresh_diagnostics(&req_queue, &sender);
+        } else if let Err(why) = publish_diagnostics(&sender, workspace.as_ref(), &manager) {
             warn!("Failed to publish diagnostics: {}", why);
         }
     })
@@ -832,19 +1598,176 @@ fn create_static_debouncer(
 fn create_chktex_debouncer(
     manager: Arc<Mutex<DiagnosticsManager>>,
     conn: &Connection,
+    req_queue: Arc<Mutex<ReqQueue>>,
     context: Arc<ServerContext>,
 ) -> DiagnosticsDebouncer {
     let sender = conn.sender.clone();
     DiagnosticsDebouncer::launch(Arc::clone(&context), move |workspace, document| {
-        let options = { context.options.read().unwrap().clone() };
+        let options = context.options_for(&document.uri);
         let mut manager = manager.lock().unwrap();
         manager.update_chktex(workspace.as_ref(), Arc::clone(&document.uri), &options);
-        if let Err(why) = publish_diagnostics(&sender, workspace.as_ref(), &manager) {
+        if manager.pull_support && manager.refresh_support {
+            drop(manager);
+            refresh_diagnostics(&req_queue, &sender);
+        } else if let Err(why) = publish_diagnostics(&sender, workspace.as_ref(), &manager) {
             warn!("Failed to publish diagnostics: {}", why);
         }
     })
 }
 
+/// Finds the documents of the projects that link to the given document.
+fn find_dependent_documents(workspace: &dyn Workspace, uri: &Uri) -> Vec<Arc<Document>> {
+    let mut visited = FxHashSet::default();
+    let mut documents = Vec::new();
+    for document in workspace.documents() {
+        let links_to_uri = document.data.as_latex().map_or(false, |data| {
+            data.extras
+                .explicit_links
+                .iter()
+                .any(|link| link.targets.iter().any(|target| target.as_ref() == uri))
+        });
+
+        if !links_to_uri {
+            continue;
+        }
+
+        if let Some(subset) = workspace.subset(Arc::clone(&document.uri)) {
+            for document in subset.documents {
+                if visited.insert(Arc::clone(&document.uri)) {
+                    documents.push(document);
+                }
+            }
+        }
+    }
+
+    documents
+}
+
+/// Analyzes all documents again after the options have changed.
+fn reanalyze_documents(workspace: Arc<dyn Workspace>, sender: &Sender<DiagnosticsMessage>) {
+    for document in workspace.documents() {
+        let message = DiagnosticsMessage::Analyze {
+            workspace: Arc::clone(&workspace),
+            document,
+        };
+        sender.send(message).unwrap();
+    }
+}
+
+/// Asks the client to watch the relevant files of the workspace.
+/// Falls back to the server-side file watcher if the registration fails.
+fn register_file_watchers(
+    req_queue: &Mutex<ReqQueue>,
+    sender: &Sender<lsp_server::Message>,
+    context: &ServerContext,
+) {
+    if !context
+        .client_capabilities
+        .lock()
+        .unwrap()
+        .has_file_watching_support()
+    {
+        return;
+    }
+
+    let options = DidChangeWatchedFilesRegistrationOptions {
+        watchers: vec![FileSystemWatcher {
+            glob_pattern: "**/*.{tex,bib,aux,bbl,log,sty,cls}".into(),
+            kind: None,
+        }],
+    };
+
+    let params = RegistrationParams {
+        registrations: vec![Registration {
+            id: "watched-files".to_string(),
+            method: DidChangeWatchedFiles::METHOD.to_string(),
+            register_options: Some(serde_json::to_value(options).unwrap()),
+        }],
+    };
+
+    // Until the registration succeeds, the server watches the directories of the documents itself.
+    match send_request::<RegisterCapability>(req_queue, sender, params) {
+        Ok(()) => context.client_watches_files.store(true, Ordering::SeqCst),
+        Err(why) => warn!(
+            "Failed to register \"{}\" notification: {}",
+            DidChangeWatchedFiles::METHOD,
+            why
+        ),
+    }
+}
+
+fn index_workspace(
+    req_queue: &Mutex<ReqQueue>,
+    sender: &Sender<lsp_server::Message>,
+    workspace: &dyn Workspace,
+    context: &ServerContext,
+    folders: &[Arc<Uri>],
+) {
+    let files = find_indexable_files(context, folders);
+    if files.is_empty() {
+        return;
+    }
+
+    let token = NumberOrString::String(format!("texlab-index-{}", Uuid::new_v4()));
+    let supports_progress = {
+        context
+            .client_capabilities
+            .lock()
+            .unwrap()
+            .has_work_done_progress_support()
+    } && send_request::<WorkDoneProgressCreate>(
+        req_queue,
+        sender,
+        WorkDoneProgressCreateParams {
+            token: token.clone(),
+        },
+    )
+    .is_ok();
+
+    let send_progress = |progress| {
+        if supports_progress {
+            let params = ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            };
+
+            if let Err(why) = send_notification::<Progress>(sender, params) {
+                warn!("Failed to report indexing progress: {}", why);
+            }
+        }
+    };
+
+    let file_count = files.len();
+    send_progress(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+        title: "Indexing".into(),
+        cancellable: Some(false),
+        message: None,
+        percentage: Some(0),
+    }));
+
+    let last_percentage = AtomicU32::new(0);
+    index_files(workspace, files, |count| {
+        let percentage = (count * 100 / file_count) as u32;
+        if last_percentage.fetch_max(percentage, Ordering::SeqCst) < percentage {
+            send_progress(WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: None,
+                message: Some(format!("{}/{} files", count, file_count)),
+                percentage: Some(percentage),
+            }));
+        }
+    });
+
+    send_progress(WorkDoneProgress::End(WorkDoneProgressEnd { message: None }));
+}
+
+fn refresh_diagnostics(req_queue: &Mutex<ReqQueue>, sender: &Sender<lsp_server::Message>) {
+    if let Err(why) =
+        send_request_without_waiting::<WorkspaceDiagnosticRefresh>(req_queue, sender, ())
+    {
+        warn!("Failed to refresh diagnostics: {}", why);
+    }
+}
+
 fn publish_diagnostics(
     sender: &Sender<lsp_server::Message>,
     workspace: &dyn Workspace,
@@ -864,6 +1787,130 @@ fn publish_diagnostics(
     Ok(())
 }
 
+fn clear_diagnostics(sender: &Sender<lsp_server::Message>, uri: &Uri) -> Result<()> {
+    send_notification::<PublishDiagnostics>(
+        sender,
+        PublishDiagnosticsParams {
+            uri: uri.clone().into(),
+            version: None,
+            diagnostics: Vec::new(),
+        },
+    )
+}
+
+/// Picks the first position encoding offered by the client (`general.positionEncodings`)
+/// that is supported by the server. Falls back to UTF-16 as required by the specification.
+fn negotiate_position_encoding(params: &serde_json::Value) -> PositionEncoding {
+    params
+        .pointer("/capabilities/general/positionEncodings")
+        .and_then(|encodings| encodings.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|encoding| encoding.as_str())
+        .find_map(PositionEncoding::from_label)
+        .unwrap_or_default()
+}
+
+/// Applies the changes of a `textDocument/didChange` notification.
+/// Returns the new text and the range of the old text that has been replaced
+/// (`None` if the client sent the whole text).
+fn apply_document_changes(
+    old_text: &str,
+    old_line_index: &LineIndex,
+    changes: Vec<TextDocumentContentChangeEvent>,
+) -> (String, Option<TextRange>) {
+    let mut text = old_text.to_string();
+    let mut line_index = Cow::Borrowed(old_line_index);
+    let mut is_stale = false;
+    // The edited region as (start, end in the old text, end in the new text)
+    let mut edit: Option<(usize, usize, usize)> = None;
+    let mut is_full = false;
+    for change in changes {
+        match change.range {
+            Some(range) => {
+                if is_stale {
+                    line_index =
+                        Cow::Owned(LineIndex::with_encoding(&text, old_line_index.encoding()));
+                }
+
+                let start = clamp_position(&text, &line_index, range.start);
+                let end = clamp_position(&text, &line_index, range.end).max(start);
+                text.replace_range(start..end, &change.text);
+
+                let (edit_start, old_end, new_end) = edit.unwrap_or((start, end, end));
+                let (new_end, old_end) = if end > new_end {
+                    (end, old_end + end - new_end)
+                } else {
+                    (new_end, old_end)
+                };
+
+                edit = Some((
+                    edit_start.min(start),
+                    old_end,
+                    new_end - (end - start) + change.text.len(),
+                ));
+            }
+            None => {
+                text = change.text;
+                is_full = true;
+            }
+        };
+
+        // The ranges of the following changes refer to the modified text.
+        is_stale = true;
+    }
+
+    let edit = edit.filter(|_| !is_full).map(|(start, old_end, _)| {
+        TextRange::new(
+            TextSize::try_from(start).unwrap(),
+            TextSize::try_from(old_end).unwrap(),
+        )
+    });
+
+    (text, edit)
+}
+
+/// Converts a position of the client into an offset of the text.
+/// Lines after the end of the text are clamped to the last line
+/// and columns after the end of a line to the end of the line.
+fn clamp_position(text: &str, line_index: &LineIndex, position: Position) -> usize {
+    let line = position.line.min(line_index.line_count() as u32 - 1);
+    let line_start = usize::from(line_index.offset_lsp(Position::new(line, 0)));
+    let line_text = text[line_start..]
+        .split('\n')
+        .next()
+        .unwrap()
+        .trim_end_matches('\r');
+    let line_end = line_start + line_text.len();
+
+    let max_character = line_index
+        .line_col_lsp(TextSize::try_from(line_end).unwrap())
+        .character;
+    let character = position.character.min(max_character);
+    let mut offset =
+        usize::from(line_index.offset_lsp(Position::new(line, character))).min(line_end);
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+fn parse_command_argument<T: DeserializeOwned>(params: ExecuteCommandParams) -> Option<T> {
+    let argument = params.arguments.into_iter().next()?;
+    serde_json::from_value(argument).ok()
+}
+
+/// Removes a finished request from the queue and records its latency.
+fn complete_request(req_queue: &Mutex<ReqQueue>, context: &ServerContext, id: &RequestId) {
+    let data = { req_queue.lock().unwrap().incoming.complete(id.clone()) };
+    if let Some(data) = data {
+        context
+            .statistics
+            .record_request(&data.method, data.start_time.elapsed());
+    }
+}
+
 fn cancel_response(id: RequestId) -> lsp_server::Response {
     lsp_server::Response::new_err(
         id,
@@ -891,3 +1938,201 @@ impl lsp_types::request::Request for ForwardSearchRequest {
 
     const METHOD: &'static str = "textDocument/forwardSearch";
 }
+
+struct DependencyGraphRequest;
+
+impl lsp_types::request::Request for DependencyGraphRequest {
+    type Params = DependencyGraphParams;
+
+    type Result = DependencyGraph;
+
+    const METHOD: &'static str = "texlab/dependencyGraph";
+}
+
+struct StatisticsRequest;
+
+impl lsp_types::request::Request for StatisticsRequest {
+    type Params = ();
+
+    type Result = StatisticsReport;
+
+    const METHOD: &'static str = "$/texlab/stats";
+}
+
+struct InlayHintRequest;
+
+impl lsp_types::request::Request for InlayHintRequest {
+    type Params = InlayHintParams;
+
+    type Result = Vec<InlayHint>;
+
+    const METHOD: &'static str = "textDocument/inlayHint";
+}
+
+struct DocumentDiagnosticRequest;
+
+impl lsp_types::request::Request for DocumentDiagnosticRequest {
+    type Params = DocumentDiagnosticParams;
+
+    type Result = DocumentDiagnosticReport;
+
+    const METHOD: &'static str = "textDocument/diagnostic";
+}
+
+struct WorkspaceDiagnosticRequest;
+
+impl lsp_types::request::Request for WorkspaceDiagnosticRequest {
+    type Params = WorkspaceDiagnosticParams;
+
+    type Result = WorkspaceDiagnosticReport;
+
+    const METHOD: &'static str = "workspace/diagnostic";
+}
+
+struct WorkspaceDiagnosticRefresh;
+
+impl lsp_types::request::Request for WorkspaceDiagnosticRefresh {
+    type Params = ();
+
+    type Result = ();
+
+    const METHOD: &'static str = "workspace/diagnostic/refresh";
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RangeExt;
+
+    use super::*;
+
+    fn apply(text: &str, changes: Vec<TextDocumentContentChangeEvent>) -> String {
+        let (new_text, edit) = apply_document_changes(text, &LineIndex::new(text), changes);
+        if let Some(edit) = edit {
+            let start = usize::from(edit.start());
+            let new_end = usize::from(edit.end()) + new_text.len() - text.len();
+            let mut expected_text = text.to_string();
+            expected_text.replace_range(start..usize::from(edit.end()), &new_text[start..new_end]);
+            assert_eq!(expected_text, new_text);
+        }
+
+        new_text
+    }
+
+    #[test]
+    fn test_apply_document_changes_full() {
+        let actual = apply(
+            "foo\nbar",
+            vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "baz".to_string(),
+            }],
+        );
+        assert_eq!(actual, "baz");
+    }
+
+    #[test]
+    fn test_apply_document_changes_incremental() {
+        let actual = apply(
+            "foo\nbar\nbaz",
+            vec![TextDocumentContentChangeEvent {
+                range: Some(Range::new_simple(1, 1, 1, 3)),
+                range_length: None,
+                text: "ox".to_string(),
+            }],
+        );
+        assert_eq!(actual, "foo\nbox\nbaz");
+    }
+
+    #[test]
+    fn test_apply_document_changes_sequential() {
+        let actual = apply(
+            "\\section{Foo}",
+            vec![
+                TextDocumentContentChangeEvent {
+                    range: Some(Range::new_simple(0, 13, 0, 13)),
+                    range_length: None,
+                    text: "\n\\label{sec:foo}".to_string(),
+                },
+                TextDocumentContentChangeEvent {
+                    range: Some(Range::new_simple(1, 11, 1, 14)),
+                    range_length: None,
+                    text: "bar".to_string(),
+                },
+            ],
+        );
+        assert_eq!(actual, "\\section{Foo}\n\\label{sec:bar}");
+    }
+
+    #[test]
+    fn test_apply_document_changes_non_ascii() {
+        let actual = apply(
+            "äöü\n𝔸foo",
+            vec![TextDocumentContentChangeEvent {
+                range: Some(Range::new_simple(1, 2, 1, 5)),
+                range_length: None,
+                text: "bar".to_string(),
+            }],
+        );
+        assert_eq!(actual, "äöü\n𝔸bar");
+    }
+
+    #[test]
+    fn test_apply_document_changes_utf8() {
+        let text = "äöü\n𝔸foo";
+        let (actual, _) = apply_document_changes(
+            text,
+            &LineIndex::with_encoding(text, PositionEncoding::Utf8),
+            vec![TextDocumentContentChangeEvent {
+                range: Some(Range::new_simple(1, 4, 1, 7)),
+                range_length: None,
+                text: "bar".to_string(),
+            }],
+        );
+        assert_eq!(actual, "äöü\n𝔸bar");
+    }
+
+    #[test]
+    fn test_apply_document_changes_out_of_range() {
+        let actual = apply(
+            "foo\näöü\nbar",
+            vec![
+                TextDocumentContentChangeEvent {
+                    range: Some(Range::new_simple(1, 2, 1, 100)),
+                    range_length: None,
+                    text: "x".to_string(),
+                },
+                TextDocumentContentChangeEvent {
+                    range: Some(Range::new_simple(10, 0, 10, 5)),
+                    range_length: None,
+                    text: "baz".to_string(),
+                },
+            ],
+        );
+        assert_eq!(actual, "foo\näöx\nbaz");
+    }
+
+    #[test]
+    fn test_negotiate_position_encoding() {
+        let params = serde_json::json!({
+            "capabilities": {
+                "general": {
+                    "positionEncodings": ["utf-32", "utf-8", "utf-16"]
+                }
+            }
+        });
+        assert_eq!(
+            negotiate_position_encoding(&params),
+            PositionEncoding::Utf32
+        );
+    }
+
+    #[test]
+    fn test_negotiate_position_encoding_default() {
+        let params = serde_json::json!({ "capabilities": {} });
+        assert_eq!(
+            negotiate_position_encoding(&params),
+            PositionEncoding::Utf16
+        );
+    }
+}
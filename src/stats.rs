@@ -0,0 +1,213 @@
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+use lsp_types::Url;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Document, DocumentLanguage, Workspace};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingStatistics {
+    pub count: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+impl TimingStatistics {
+    pub fn record(&mut self, duration: Duration) {
+        let ms = duration_ms(duration);
+        self.count += 1;
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStatistics {
+    pub uri: Url,
+    pub language: String,
+    /// See `Document::estimated_memory`; this is an estimate, not a measurement.
+    pub estimated_memory: usize,
+    pub parse_time_ms: f64,
+    pub analysis_time_ms: f64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStatistics {
+    /// Pending messages of the diagnostics debouncer
+    pub diagnostics: usize,
+    /// Pending messages of the `chktex` debouncer
+    pub chktex: usize,
+    /// Requests and notifications waiting for a worker thread
+    pub pending_tasks: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsReport {
+    pub document_counts: BTreeMap<String, usize>,
+    pub documents: Vec<DocumentStatistics>,
+    pub parse_times: BTreeMap<String, TimingStatistics>,
+    pub analysis_times: BTreeMap<String, TimingStatistics>,
+    pub queues: QueueStatistics,
+    pub request_latencies: BTreeMap<String, TimingStatistics>,
+}
+
+/// Collects the timings of the server to find out why an editor session is slow.
+#[derive(Debug, Default)]
+pub struct ServerStatistics {
+    parse_times: Mutex<FxHashMap<DocumentLanguage, TimingStatistics>>,
+    analysis_times: Mutex<FxHashMap<DocumentLanguage, TimingStatistics>>,
+    request_latencies: Mutex<FxHashMap<String, TimingStatistics>>,
+}
+
+impl ServerStatistics {
+    pub fn record_document(&self, document: &Document) {
+        let language = document.language();
+        self.parse_times
+            .lock()
+            .unwrap()
+            .entry(language)
+            .or_default()
+            .record(document.timings.parse);
+
+        if language == DocumentLanguage::Latex {
+            self.analysis_times
+                .lock()
+                .unwrap()
+                .entry(language)
+                .or_default()
+                .record(document.timings.analysis);
+        }
+    }
+
+    pub fn record_request(&self, method: &str, latency: Duration) {
+        self.request_latencies
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .record(latency);
+    }
+
+    pub fn report(&self, workspace: &dyn Workspace, queues: QueueStatistics) -> StatisticsReport {
+        let mut document_counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut documents = Vec::new();
+        for document in workspace.documents() {
+            let language = language_name(document.language());
+            *document_counts.entry(language.to_string()).or_default() += 1;
+            documents.push(DocumentStatistics {
+                uri: document.uri.as_ref().clone().into(),
+                language: language.to_string(),
+                estimated_memory: document.estimated_memory(),
+                parse_time_ms: duration_ms(document.timings.parse),
+                analysis_time_ms: duration_ms(document.timings.analysis),
+            });
+        }
+
+        documents.sort_by(|a, b| a.uri.as_str().cmp(b.uri.as_str()));
+
+        StatisticsReport {
+            document_counts,
+            documents,
+            parse_times: by_language(&self.parse_times.lock().unwrap()),
+            analysis_times: by_language(&self.analysis_times.lock().unwrap()),
+            queues,
+            request_latencies: self
+                .request_latencies
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(method, timing)| (method.clone(), *timing))
+                .collect(),
+        }
+    }
+}
+
+fn by_language(
+    timings: &FxHashMap<DocumentLanguage, TimingStatistics>,
+) -> BTreeMap<String, TimingStatistics> {
+    timings
+        .iter()
+        .map(|(language, timing)| (language_name(*language).to_string(), *timing))
+        .collect()
+}
+
+fn language_name(language: DocumentLanguage) -> &'static str {
+    match language {
+        DocumentLanguage::Latex => "latex",
+        DocumentLanguage::Bibtex => "bibtex",
+        DocumentLanguage::BuildLog => "buildLog",
+    }
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{create_workspace_fast, ServerContext, Uri, WorkspaceSource};
+
+    use super::*;
+
+    #[test]
+    fn test_timing_statistics() {
+        let mut timing = TimingStatistics::default();
+        timing.record(Duration::from_millis(10));
+        timing.record(Duration::from_millis(30));
+        assert_eq!(timing.count, 2);
+        assert!((timing.total_ms - 40.0).abs() < 1e-6);
+        assert!((timing.max_ms - 30.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_report() {
+        let context = Arc::new(ServerContext::new(std::env::temp_dir()));
+        let workspace: Arc<dyn Workspace> =
+            Arc::new(create_workspace_fast(Arc::clone(&context)).unwrap());
+
+        for (name, language) in &[
+            ("main.tex", DocumentLanguage::Latex),
+            ("chapter.tex", DocumentLanguage::Latex),
+            ("main.bib", DocumentLanguage::Bibtex),
+        ] {
+            workspace.open(
+                Arc::new(Uri::parse(&format!("http://example.com/{}", name)).unwrap()),
+                r#"\section{Foo}"#.into(),
+                *language,
+                WorkspaceSource::Client,
+            );
+        }
+
+        context
+            .statistics
+            .record_request("textDocument/hover", Duration::from_millis(5));
+
+        let report = context.statistics.report(
+            workspace.as_ref(),
+            QueueStatistics {
+                diagnostics: 1,
+                ..QueueStatistics::default()
+            },
+        );
+
+        assert_eq!(report.document_counts["latex"], 2);
+        assert_eq!(report.document_counts["bibtex"], 1);
+        assert_eq!(report.documents.len(), 3);
+        assert!(report
+            .documents
+            .iter()
+            .all(|document| document.estimated_memory > 0));
+        assert_eq!(report.parse_times["latex"].count, 2);
+        assert_eq!(report.analysis_times["latex"].count, 2);
+        assert_eq!(report.parse_times["bibtex"].count, 1);
+        assert_eq!(report.queues.diagnostics, 1);
+        assert_eq!(report.request_latencies["textDocument/hover"].count, 1);
+    }
+}
@@ -1,3 +1,4 @@
+mod citation_label;
 mod command;
 mod distro_file;
 mod environment;
@@ -8,10 +9,15 @@ mod label_number;
 mod theorem;
 mod types;
 
+use std::{mem, sync::Arc};
+
+use cstree::TextRange;
+
 use crate::syntax::latex;
 
 pub use self::types::*;
 use self::{
+    citation_label::analyze_citation_label,
     command::analyze_command,
     environment::analyze_begin,
     explicit_link::{analyze_import, analyze_include},
@@ -23,7 +29,71 @@ use self::{
 
 pub fn analyze(context: &mut LatexAnalyzerContext, root: &latex::SyntaxNode) {
     analyze_implicit_links(context);
+    analyze_nodes(context, root);
+    context.extras.has_document_environment = context.extras.environment_names.contains("document");
+}
+
+/// Updates the `Extras` of a document after `old_node` has been replaced by `new_node`.
+/// Only the positions inside and behind the replaced node are updated.
+/// All other information is shared with `old_extras`.
+///
+/// Returns `None` if the edit affects document-wide information like
+/// the defined commands or if a position encloses the replaced node.
+/// In this case, the whole document has to be analyzed again.
+pub fn analyze_incremental(
+    context: &mut LatexAnalyzerContext,
+    old_extras: &Extras,
+    old_node: &latex::SyntaxNode,
+    new_node: &latex::SyntaxNode,
+) -> Option<()> {
+    let old_part = analyze_part(context, old_node);
+    let new_part = analyze_part(context, new_node);
+    if old_part.command_names != new_part.command_names
+        || old_part.environment_names != new_part.environment_names
+        || old_part.label_numbers_by_name != new_part.label_numbers_by_name
+        || old_part.citation_labels_by_key != new_part.citation_labels_by_key
+        || old_part.theorem_environments != new_part.theorem_environments
+    {
+        return None;
+    }
+
+    let old_range = old_node.text_range();
+    let new_range = new_node.text_range();
+    let explicit_links = splice_ranged(
+        &old_extras.explicit_links,
+        old_part.explicit_links.len(),
+        new_part.explicit_links,
+        old_range,
+        new_range,
+        |link| &mut link.stem_range,
+    )?;
+
+    let label_names = splice_ranged(
+        &old_extras.label_names,
+        old_part.label_names.len(),
+        new_part.label_names,
+        old_range,
+        new_range,
+        |label| &mut label.range,
+    )?;
+
+    context.extras = Extras {
+        implicit_links: Arc::clone(&old_extras.implicit_links),
+        explicit_links,
+        has_document_environment: old_extras.has_document_environment,
+        command_names: Arc::clone(&old_extras.command_names),
+        environment_names: Arc::clone(&old_extras.environment_names),
+        label_names,
+        label_numbers_by_name: Arc::clone(&old_extras.label_numbers_by_name),
+        citation_labels_by_key: Arc::clone(&old_extras.citation_labels_by_key),
+        theorem_environments: Arc::clone(&old_extras.theorem_environments),
+    };
+    Some(())
+}
+
+fn analyze_nodes(context: &mut LatexAnalyzerContext, root: &latex::SyntaxNode) {
     for node in root.descendants() {
+        analyze_citation_label(context, node);
         analyze_command(context, node)
             .or_else(|| analyze_begin(context, node))
             .or_else(|| analyze_include(context, node))
@@ -32,5 +102,147 @@ pub fn analyze(context: &mut LatexAnalyzerContext, root: &latex::SyntaxNode) {
             .or_else(|| analyze_label_number(context, node))
             .or_else(|| analyze_theorem_definition(context, node));
     }
-    context.extras.has_document_environment = context.extras.environment_names.contains("document");
+}
+
+fn analyze_part(context: &mut LatexAnalyzerContext, node: &latex::SyntaxNode) -> Extras {
+    let extras = mem::take(&mut context.extras);
+    analyze_nodes(context, node);
+    mem::replace(&mut context.extras, extras)
+}
+
+/// Replaces the items inside of `old_range` with `new_items` and moves the items behind it.
+/// Returns `None` if an item overlaps the boundaries of `old_range` or if the old node
+/// did not produce exactly `old_count` of the replaced items.
+fn splice_ranged<T: Clone>(
+    items: &[T],
+    old_count: usize,
+    new_items: Vec<T>,
+    old_range: TextRange,
+    new_range: TextRange,
+    range_of: impl Fn(&mut T) -> &mut TextRange,
+) -> Option<Vec<T>> {
+    let mut new_items = Some(new_items);
+    let mut result = Vec::with_capacity(items.len());
+    let mut removed_count = 0;
+    for item in items {
+        let mut item = item.clone();
+        let range = range_of(&mut item);
+        if old_range.contains_range(*range) {
+            removed_count += 1;
+        } else if range.end() <= old_range.start() {
+            result.push(item);
+        } else if range.start() >= old_range.end() {
+            result.extend(new_items.take().into_iter().flatten());
+            *range = TextRange::new(
+                range.start() - old_range.end() + new_range.end(),
+                range.end() - old_range.end() + new_range.end(),
+            );
+            result.push(item);
+        } else {
+            return None;
+        }
+    }
+
+    if removed_count != old_count {
+        return None;
+    }
+
+    result.extend(new_items.into_iter().flatten());
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{ServerContext, Uri};
+
+    use super::*;
+
+    fn create_context() -> LatexAnalyzerContext {
+        let uri = Arc::new(Uri::parse("http://www.example.com/main.tex").unwrap());
+        LatexAnalyzerContext {
+            inner: Arc::new(ServerContext::new(std::env::temp_dir())),
+            document_uri: Arc::clone(&uri),
+            base_uri: uri,
+            extras: Extras::default(),
+        }
+    }
+
+    fn analyze_text(text: &str) -> Extras {
+        let mut context = create_context();
+        analyze(&mut context, &latex::parse(text).root);
+        context.extras
+    }
+
+    fn analyze_edit(old_text: &str, edit: TextRange, insert: &str) -> Option<Extras> {
+        let old_parse = latex::parse(old_text);
+        let reparse = latex::reparse(&old_parse.root, &old_parse.interner, edit, insert)?;
+        let mut context = create_context();
+        analyze_incremental(
+            &mut context,
+            &analyze_text(old_text),
+            &reparse.old_node,
+            &reparse.new_node,
+        )?;
+        Some(context.extras)
+    }
+
+    fn check_incremental(old_text: &str, old: &str, new: &str) {
+        let start = old_text.find(old).unwrap();
+        let edit = TextRange::at((start as u32).into(), (old.len() as u32).into());
+        let actual = analyze_edit(old_text, edit, new).unwrap();
+        let expected = analyze_text(&old_text.replacen(old, new, 1));
+
+        let links = |extras: &Extras| {
+            extras
+                .explicit_links
+                .iter()
+                .map(|link| (link.stem.clone(), link.stem_range, link.kind))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(links(&actual), links(&expected));
+        assert_eq!(actual.label_names, expected.label_names);
+        assert_eq!(actual.command_names, expected.command_names);
+        assert_eq!(actual.environment_names, expected.environment_names);
+        assert_eq!(actual.label_numbers_by_name, expected.label_numbers_by_name);
+        assert_eq!(
+            actual.has_document_environment,
+            expected.has_document_environment
+        );
+    }
+
+    #[test]
+    fn test_incremental_labels() {
+        check_incremental(
+            "\\label{foo}\n\\begin{a}\\label{bar}\\end{a}\n\\input{baz}\\ref{qux}",
+            "bar",
+            "foobar",
+        );
+    }
+
+    #[test]
+    fn test_incremental_multiple_nodes() {
+        check_incremental(
+            "\\begin{a}\\label{foo}\\ref{bar}\\input{baz}\\end{a}\n\\label{qux}\\input{quux}",
+            "\\ref{bar}\\input",
+            "\\input{x}\\ref",
+        );
+    }
+
+    #[test]
+    fn test_incremental_removal() {
+        check_incremental(
+            "\\begin{document}\\label{foo}\\ref{bar}\\label{baz}\\end{document}\\ref{qux}",
+            "\\ref{bar}\\label{baz}",
+            "",
+        );
+    }
+
+    #[test]
+    fn test_incremental_new_command() {
+        let edit = TextRange::new(5.into(), 8.into());
+        assert!(analyze_edit("\\foo{bar}", edit, "\\baz").is_none());
+    }
 }
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use crate::syntax::{
+    latex::{self, HasCurly},
+    CstNode,
+};
+
+use super::LatexAnalyzerContext;
+
+pub fn analyze_citation_label(
+    context: &mut LatexAnalyzerContext,
+    node: &latex::SyntaxNode,
+) -> Option<()> {
+    let command = latex::GenericCommand::cast(node)?;
+    match command.name()?.text() {
+        "\\bibcite" => analyze_bibcite(context, command),
+        "\\bibitem" => analyze_bibitem(context, command),
+        _ => None,
+    }
+}
+
+fn analyze_bibcite(
+    context: &mut LatexAnalyzerContext,
+    command: latex::GenericCommand,
+) -> Option<()> {
+    let mut groups = command
+        .syntax()
+        .children()
+        .filter_map(latex::CurlyGroup::cast);
+
+    let key = groups.next()?.content_text()?;
+    let text = groups
+        .next()?
+        .syntax()
+        .descendants()
+        .find(|node| node.kind() == latex::TEXT)?
+        .text()
+        .to_string();
+
+    Arc::make_mut(&mut context.extras.citation_labels_by_key).insert(key, text.trim().to_string());
+    Some(())
+}
+
+/// Handles the `\bibitem[label]{key}` entries of a `.bbl` file.
+/// Without an explicit label, the entries are numbered in order of appearance.
+fn analyze_bibitem(
+    context: &mut LatexAnalyzerContext,
+    command: latex::GenericCommand,
+) -> Option<()> {
+    let key = command
+        .syntax()
+        .children()
+        .find_map(latex::CurlyGroup::cast)?
+        .content_text()?;
+
+    let text = match command
+        .syntax()
+        .children()
+        .find_map(latex::MixedGroup::cast)
+    {
+        Some(group) => {
+            if group.left_delim()?.kind() != latex::L_BRACK
+                || group.right_delim()?.kind() != latex::R_BRACK
+            {
+                return None;
+            }
+
+            let mut text = String::new();
+            for token in group
+                .syntax()
+                .descendants_with_tokens()
+                .filter_map(|child| child.into_token())
+                .filter(|token| {
+                    !matches!(
+                        token.kind(),
+                        latex::COMMENT | latex::L_BRACK | latex::R_BRACK
+                    )
+                })
+            {
+                text.push_str(token.text());
+            }
+            text.trim().to_string()
+        }
+        None => (context.extras.citation_labels_by_key.len() + 1).to_string(),
+    };
+
+    Arc::make_mut(&mut context.extras.citation_labels_by_key).insert(key, text);
+    Some(())
+}
@@ -1,12 +1,12 @@
+use std::sync::Arc;
+
 use crate::syntax::{latex, CstNode};
 
 use super::LatexAnalyzerContext;
 
 pub fn analyze_command(context: &mut LatexAnalyzerContext, node: &latex::SyntaxNode) -> Option<()> {
     let command = latex::GenericCommand::cast(node)?;
-    context
-        .extras
-        .command_names
-        .insert(command.name()?.text().into());
+    let name = command.name()?.text().into();
+    Arc::make_mut(&mut context.extras.command_names).insert(name);
     Some(())
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::syntax::{latex, CstNode};
 
 use super::LatexAnalyzerContext;
@@ -5,7 +7,6 @@ use super::LatexAnalyzerContext;
 pub fn analyze_begin(context: &mut LatexAnalyzerContext, node: &latex::SyntaxNode) -> Option<()> {
     let begin = latex::Begin::cast(node)?;
     let name = begin.name()?.key()?.to_string();
-    let extras = &mut context.extras;
-    extras.environment_names.insert(name.into());
+    Arc::make_mut(&mut context.extras.environment_names).insert(name);
     Some(())
 }
@@ -2,12 +2,15 @@ use std::sync::Arc;
 
 use crate::Uri;
 
-use super::LatexAnalyzerContext;
+use super::{ImplicitLinks, LatexAnalyzerContext};
 
 pub fn analyze_implicit_links(context: &mut LatexAnalyzerContext) {
-    context.extras.implicit_links.aux = find_by_extension(context, "aux").unwrap_or_default();
-    context.extras.implicit_links.log = find_by_extension(context, "log").unwrap_or_default();
-    context.extras.implicit_links.pdf = find_by_extension(context, "pdf").unwrap_or_default();
+    context.extras.implicit_links = Arc::new(ImplicitLinks {
+        aux: find_by_extension(context, "aux").unwrap_or_default(),
+        bbl: find_by_extension(context, "bbl").unwrap_or_default(),
+        log: find_by_extension(context, "log").unwrap_or_default(),
+        pdf: find_by_extension(context, "pdf").unwrap_or_default(),
+    });
 }
 
 fn find_by_extension(context: &LatexAnalyzerContext, extension: &str) -> Option<Vec<Arc<Uri>>> {
@@ -18,7 +21,7 @@ fn find_by_extension(context: &LatexAnalyzerContext, extension: &str) -> Option<
         let file_stem = file_path.file_stem()?;
         let aux_name = format!("{}.{}", file_stem.to_str()?, extension);
 
-        let options = context.inner.options.read().unwrap();
+        let options = context.inner.options_for(&context.document_uri);
         if let Some(root_dir) = options.root_directory.as_ref() {
             let path = context
                 .inner
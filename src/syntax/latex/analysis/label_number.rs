@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::syntax::{latex, CstNode};
 
 use super::LatexAnalyzerContext;
@@ -17,6 +19,6 @@ pub fn analyze_label_number(
         .text()
         .to_string();
 
-    context.extras.label_numbers_by_name.insert(name, text);
+    Arc::make_mut(&mut context.extras.label_numbers_by_name).insert(name, text);
     Some(())
 }
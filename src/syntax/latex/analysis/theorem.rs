@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::syntax::{
     latex::{self, HasCurly},
     CstNode,
@@ -14,9 +16,7 @@ pub fn analyze_theorem_definition(
     let description = theorem.description()?;
     let description = description.content_text()?;
 
-    context
-        .extras
-        .theorem_environments
+    Arc::make_mut(&mut context.extras.theorem_environments)
         .push(TheoremEnvironment { name, description });
 
     Some(())
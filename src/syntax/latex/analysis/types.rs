@@ -14,21 +14,27 @@ pub struct LatexAnalyzerContext {
     pub extras: Extras,
 }
 
+/// The information extracted from a LaTeX document.
+///
+/// The fields without positions are shared between the versions of a document
+/// because most edits do not change them.
 #[derive(Debug, Clone, Default)]
 pub struct Extras {
-    pub implicit_links: ImplicitLinks,
+    pub implicit_links: Arc<ImplicitLinks>,
     pub explicit_links: Vec<ExplicitLink>,
     pub has_document_environment: bool,
-    pub command_names: FxHashSet<SmolStr>,
-    pub environment_names: FxHashSet<String>,
+    pub command_names: Arc<FxHashSet<SmolStr>>,
+    pub environment_names: Arc<FxHashSet<String>>,
     pub label_names: Vec<LabelName>,
-    pub label_numbers_by_name: FxHashMap<String, String>,
-    pub theorem_environments: Vec<TheoremEnvironment>,
+    pub label_numbers_by_name: Arc<FxHashMap<String, String>>,
+    pub citation_labels_by_key: Arc<FxHashMap<String, String>>,
+    pub theorem_environments: Arc<Vec<TheoremEnvironment>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default, Hash)]
 pub struct ImplicitLinks {
     pub aux: Vec<Arc<Uri>>,
+    pub bbl: Vec<Arc<Uri>>,
     pub log: Vec<Arc<Uri>>,
     pub pdf: Vec<Arc<Uri>>,
 }
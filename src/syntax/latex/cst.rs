@@ -538,11 +538,41 @@ impl<'a> CommandDefinition<'a> {
         self.syntax().children().find_map(CurlyGroupCommand::cast)
     }
 
+    pub fn argument_count(&self) -> Option<BrackGroupWord<'a>> {
+        self.syntax().children().find_map(BrackGroupWord::cast)
+    }
+
+    /// The default value of the first argument, which makes this argument optional.
+    pub fn default_argument(&self) -> Option<BrackGroup<'a>> {
+        self.syntax().children().find_map(BrackGroup::cast)
+    }
+
     pub fn implementation(&self) -> Option<CurlyGroup<'a>> {
         self.syntax().children().find_map(CurlyGroup::cast)
     }
 }
 
+cst_node!(EnvironmentDefinition, ENVIRONMENT_DEFINITION);
+
+impl<'a> EnvironmentDefinition<'a> {
+    pub fn command(&self) -> Option<&'a SyntaxToken> {
+        self.syntax().first_token()
+    }
+
+    pub fn name(&self) -> Option<CurlyGroupWord<'a>> {
+        self.syntax().children().find_map(CurlyGroupWord::cast)
+    }
+
+    pub fn argument_count(&self) -> Option<BrackGroupWord<'a>> {
+        self.syntax().children().find_map(BrackGroupWord::cast)
+    }
+
+    /// The default value of the first argument, which makes this argument optional.
+    pub fn default_argument(&self) -> Option<BrackGroup<'a>> {
+        self.syntax().children().find_map(BrackGroup::cast)
+    }
+}
+
 cst_node!(AcronymReference, ACRONYM_REFERENCE);
 
 impl<'a> AcronymReference<'a> {
@@ -1,14 +1,60 @@
-use cstree::GreenNodeBuilder;
+use std::sync::Arc;
+
+use cstree::{
+    interning::{Resolver, Rodeo},
+    GreenNodeBuilder, TextRange,
+};
+
+use crate::syntax::CstNode;
 
 use super::{
     lexer::Lexer,
+    Environment, HasCurly,
     SyntaxKind::{self, *},
-    SyntaxNode,
+    SyntaxNode, SyntaxToken,
 };
 
 #[derive(Clone)]
 pub struct Parse {
     pub root: SyntaxNode,
+    /// The interner of the token texts. It is shared with the syntax tree
+    /// so that a reparse can intern new tokens without losing the old ones.
+    pub interner: Arc<Rodeo>,
+}
+
+#[derive(Clone)]
+pub struct Reparse {
+    pub parse: Parse,
+    pub old_node: SyntaxNode,
+    pub new_node: SyntaxNode,
+}
+
+#[derive(Debug, Clone)]
+struct SharedInterner(Arc<Rodeo>);
+
+impl<K> Resolver<K> for SharedInterner
+where
+    Rodeo: Resolver<K>,
+{
+    fn resolve<'a>(&'a self, key: &K) -> &'a str {
+        self.0.resolve(key)
+    }
+
+    fn try_resolve<'a>(&'a self, key: &K) -> Option<&'a str> {
+        self.0.try_resolve(key)
+    }
+
+    unsafe fn resolve_unchecked<'a>(&'a self, key: &K) -> &'a str {
+        self.0.resolve_unchecked(key)
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.0.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -40,6 +86,13 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn with_interner(text: &'a str, interner: Rodeo) -> Self {
+        Self {
+            lexer: Lexer::new(text),
+            builder: GreenNodeBuilder::from_interner(interner),
+        }
+    }
+
     fn eat(&mut self) {
         let (kind, text) = self.lexer.eat().unwrap();
         self.builder.token(kind.into(), text);
@@ -89,8 +142,13 @@ impl<'a> Parser<'a> {
         }
         self.builder.finish_node();
         let (green_node, interner) = self.builder.finish();
+        let interner = Arc::new(interner.unwrap());
         Parse {
-            root: SyntaxNode::new_root_with_resolver(green_node, interner.unwrap()),
+            root: SyntaxNode::new_root_with_resolver(
+                green_node,
+                SharedInterner(Arc::clone(&interner)),
+            ),
+            interner,
         }
     }
 
@@ -945,6 +1003,10 @@ impl<'a> Parser<'a> {
 
         if self.lexer.peek() == Some(L_BRACK) {
             self.brack_group_word();
+
+            if self.lexer.peek() == Some(L_BRACK) {
+                self.brack_group();
+            }
         }
 
         if self.lexer.peek() == Some(L_CURLY) {
@@ -1199,6 +1261,10 @@ impl<'a> Parser<'a> {
 
         if self.lexer.peek() == Some(L_BRACK) {
             self.brack_group_word();
+
+            if self.lexer.peek() == Some(L_BRACK) {
+                self.brack_group();
+            }
         }
 
         for _ in 0..2 {
@@ -1217,6 +1283,119 @@ pub fn parse(text: &str) -> Parse {
     Parser::new(text).parse()
 }
 
+/// Replaces the text inside `edit` with `insert` and reparses only
+/// the smallest group or environment that encloses the edit.
+/// The green nodes outside of the reparsed node are shared with the old tree,
+/// so only the path from the root to the reparsed node is rebuilt.
+///
+/// Returns `None` if the edit changes the structure of the surrounding nodes.
+/// In this case, the whole document has to be parsed again.
+pub fn reparse(
+    root: &SyntaxNode,
+    interner: &Rodeo,
+    edit: TextRange,
+    insert: &str,
+) -> Option<Reparse> {
+    let old_node = find_reparsable_node(root, edit)?;
+    let old_range = old_node.text_range();
+
+    let mut text = old_node.text().to_string();
+    let start = usize::from(edit.start() - old_range.start());
+    let end = usize::from(edit.end() - old_range.start());
+    text.replace_range(start..end, insert);
+
+    // The new tokens have to be interned next to the old ones because the old green nodes
+    // refer to the keys of the old interner.
+    let mut parser = Parser::with_interner(&text, interner.clone());
+    if parser.peek() != old_node.first_token().map(|token| token.kind()) {
+        return None;
+    }
+
+    match old_node.kind() {
+        CURLY_GROUP => parser.curly_group(),
+        _ => parser.environment(),
+    };
+
+    if parser.peek().is_some() {
+        return None;
+    }
+
+    let (green_node, interner) = parser.builder.finish();
+    let interner = Arc::new(interner?);
+    let new_subtree =
+        SyntaxNode::new_root_with_resolver(green_node, SharedInterner(Arc::clone(&interner)));
+    find_closing_curly(&new_subtree)?;
+
+    let new_root = SyntaxNode::new_root_with_resolver(
+        old_node.replace_with(new_subtree.green().clone()),
+        SharedInterner(Arc::clone(&interner)),
+    );
+
+    let new_range = TextRange::at(old_range.start(), new_subtree.text_range().len());
+    let new_node = find_node(&new_root, new_range, old_node.kind())?.clone();
+    Some(Reparse {
+        parse: Parse {
+            root: new_root,
+            interner,
+        },
+        old_node: old_node.clone(),
+        new_node,
+    })
+}
+
+fn find_reparsable_node(root: &SyntaxNode, edit: TextRange) -> Option<&SyntaxNode> {
+    let mut node = root;
+    let mut result = None;
+    loop {
+        if is_reparsable(node, edit).unwrap_or(false) {
+            result = Some(node);
+        }
+
+        match node
+            .children()
+            .find(|child| child.text_range().contains_range(edit))
+        {
+            Some(child) => node = child,
+            None => return result,
+        }
+    }
+}
+
+fn is_reparsable(node: &SyntaxNode, edit: TextRange) -> Option<bool> {
+    if !matches!(node.kind(), CURLY_GROUP | ENVIRONMENT)
+        || node
+            .ancestors()
+            .any(|node| node.kind() == ENVIRONMENT_DEFINITION)
+    {
+        return Some(false);
+    }
+
+    let opening = node.first_token()?;
+    let closing = find_closing_curly(node)?;
+    Some(opening.text_range().end() <= edit.start() && edit.end() <= closing.text_range().start())
+}
+
+fn find_closing_curly(node: &SyntaxNode) -> Option<&SyntaxToken> {
+    match node.kind() {
+        CURLY_GROUP => node
+            .children_with_tokens()
+            .filter_map(|element| element.into_token())
+            .find(|token| token.kind() == R_CURLY),
+        ENVIRONMENT => Environment::cast(node)?.end()?.name()?.right_curly(),
+        _ => None,
+    }
+}
+
+fn find_node(root: &SyntaxNode, range: TextRange, kind: SyntaxKind) -> Option<&SyntaxNode> {
+    let mut node = root;
+    while node.text_range() != range || node.kind() != kind {
+        node = node
+            .children()
+            .find(|child| child.text_range().contains_range(range))?;
+    }
+    Some(node)
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot;
@@ -1716,4 +1895,72 @@ mod tests {
             r#"\DeclareAcronym{eg}{short = e.g,long = for example,tag = abbrev}"#
         ));
     }
+
+    fn check_reparse(text: &str, start: u32, end: u32, insert: &str) -> Option<Reparse> {
+        let edit = TextRange::new(start.into(), end.into());
+        let old_parse = parse(text);
+        let result = reparse(&old_parse.root, &old_parse.interner, edit, insert)?;
+
+        let mut new_text = text.to_string();
+        new_text.replace_range(start as usize..end as usize, insert);
+        assert_eq!(
+            format!("{:#?}", result.parse.root),
+            format!("{:#?}", parse(&new_text).root)
+        );
+        Some(result)
+    }
+
+    #[test]
+    fn test_reparse_curly_group() {
+        let result = check_reparse(r#"\foo{bar}\baz{qux}"#, 6, 7, "oo").unwrap();
+        assert_eq!(
+            result.old_node.text_range(),
+            TextRange::new(4.into(), 9.into())
+        );
+        assert_eq!(
+            result.new_node.text_range(),
+            TextRange::new(4.into(), 10.into())
+        );
+    }
+
+    #[test]
+    fn test_reparse_nested_curly_group() {
+        let result = check_reparse(r#"\foo{{bar} {baz}}"#, 13, 13, "\\qux").unwrap();
+        assert_eq!(
+            result.new_node.text_range(),
+            TextRange::new(11.into(), 20.into())
+        );
+    }
+
+    #[test]
+    fn test_reparse_environment() {
+        let result =
+            check_reparse("\\begin{document}\nfoo\n\\end{document}", 20, 20, " bar").unwrap();
+        assert_eq!(result.new_node.kind(), ENVIRONMENT);
+    }
+
+    #[test]
+    fn test_reparse_environment_name() {
+        assert!(check_reparse(r#"\begin{foo}bar\end{foo}"#, 19, 22, "baz").is_some());
+    }
+
+    #[test]
+    fn test_reparse_unbalanced_curly_group() {
+        assert!(check_reparse(r#"\foo{bar} baz"#, 6, 6, "}").is_none());
+    }
+
+    #[test]
+    fn test_reparse_unbalanced_environment() {
+        assert!(check_reparse(r#"\begin{foo}bar\end{foo}"#, 14, 14, "\\end{baz}").is_none());
+    }
+
+    #[test]
+    fn test_reparse_outside_group() {
+        assert!(check_reparse(r#"foo \bar{baz}"#, 1, 2, "x").is_none());
+    }
+
+    #[test]
+    fn test_reparse_command_name() {
+        assert!(check_reparse(r#"\begin{foo}bar\end{foo}"#, 6, 6, "x").is_none());
+    }
 }
@@ -1,6 +1,7 @@
 mod api;
 mod children_expand;
 mod document;
+mod indexer;
 mod parent_expand;
 mod storage;
 mod watch;
@@ -11,7 +12,11 @@ use anyhow::Result;
 
 use crate::ServerContext;
 
-pub use self::{api::*, document::*};
+pub use self::{
+    api::*,
+    document::*,
+    indexer::{find_indexable_files, index_files},
+};
 use self::{
     children_expand::ChildrenExpander, parent_expand::ParentExpander, storage::Storage,
     watch::DocumentWatcher,
@@ -23,9 +28,17 @@ pub fn create_workspace_fast(context: Arc<ServerContext>) -> Result<impl Workspa
     Ok(workspace)
 }
 
+/// Creates a workspace containing all indexable files of the workspace folders.
+pub fn create_workspace_headless(context: Arc<ServerContext>) -> Result<impl Workspace> {
+    let workspace = create_workspace_fast(Arc::clone(&context))?;
+    let folders = { context.workspace_folders.read().unwrap().clone() };
+    index_files(&workspace, find_indexable_files(&context, &folders), |_| {});
+    Ok(workspace)
+}
+
 pub fn create_workspace_full(context: Arc<ServerContext>) -> Result<impl Workspace> {
-    let workspace = Storage::new(context);
-    let workspace = DocumentWatcher::new(Arc::new(workspace))?;
+    let workspace = Storage::new(Arc::clone(&context));
+    let workspace = DocumentWatcher::new(Arc::new(workspace), context)?;
     let workspace = ParentExpander::new(workspace);
     let workspace = ChildrenExpander::new(Arc::new(workspace));
     Ok(workspace)
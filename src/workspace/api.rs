@@ -1,8 +1,9 @@
 use std::{fs, path::PathBuf, sync::Arc};
 
 use anyhow::Result;
+use cstree::TextRange;
 
-use crate::{DocumentLanguage, Uri};
+use crate::{encoding, DocumentLanguage, ServerContext, Uri};
 
 use super::Document;
 
@@ -19,6 +20,8 @@ pub struct WorkspaceSubset {
 
 pub type OpenHandler = Arc<dyn Fn(Arc<dyn Workspace>, Arc<Document>) + Send + Sync + 'static>;
 
+pub type RemoveHandler = Arc<dyn Fn(Arc<dyn Workspace>, Arc<Uri>) + Send + Sync + 'static>;
+
 pub trait Workspace: Send + Sync {
     fn open(
         &self,
@@ -28,8 +31,17 @@ pub trait Workspace: Send + Sync {
         source: WorkspaceSource,
     ) -> Arc<Document>;
 
+    /// Updates an open document after the client has replaced the text inside `edit`,
+    /// a range of the old text. Unlike `open`, only the edited part is parsed again if possible.
+    /// Returns `None` if the document is unknown.
+    fn edit(&self, uri: Arc<Uri>, text: String, edit: TextRange) -> Option<Arc<Document>>;
+
+    fn context(&self) -> Arc<ServerContext>;
+
     fn register_open_handler(&self, handler: OpenHandler);
 
+    fn register_remove_handler(&self, handler: RemoveHandler);
+
     fn reload(&self, path: PathBuf) -> Result<Option<Arc<Document>>> {
         let uri = Arc::new(Uri::from_file_path(path.clone()).unwrap());
 
@@ -38,7 +50,8 @@ pub trait Workspace: Send + Sync {
         }
 
         let data = fs::read(&path)?;
-        let text = String::from_utf8_lossy(&data).into_owned();
+        let default_encoding = self.context().options_for(&uri).default_encoding;
+        let text = encoding::decode(&data, default_encoding.as_deref());
         if let Some(language) = DocumentLanguage::by_path(&path) {
             Ok(Some(self.open(
                 uri,
@@ -59,7 +72,8 @@ pub trait Workspace: Send + Sync {
         }
 
         let data = fs::read(&path)?;
-        let text = String::from_utf8_lossy(&data).into_owned();
+        let default_encoding = self.context().options_for(&uri).default_encoding;
+        let text = encoding::decode(&data, default_encoding.as_deref());
         if let Some(language) = DocumentLanguage::by_path(&path) {
             Ok(Some(self.open(
                 uri,
@@ -80,6 +94,10 @@ pub trait Workspace: Send + Sync {
 
     fn close(&self, uri: &Uri);
 
+    /// Removes a document that is no longer present on disk.
+    /// Documents that are opened by the client are kept.
+    fn remove(&self, uri: &Uri);
+
     fn is_open(&self, uri: &Uri) -> bool;
 
     fn subset(&self, uri: Arc<Uri>) -> Option<WorkspaceSubset>;
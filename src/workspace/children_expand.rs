@@ -1,10 +1,11 @@
 use std::sync::Arc;
 
+use cstree::TextRange;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use crate::{
-    component_db::COMPONENT_DATABASE, Document, DocumentLanguage, OpenHandler, Uri, Workspace,
-    WorkspaceSource, WorkspaceSubset,
+    component_db::COMPONENT_DATABASE, Document, DocumentLanguage, OpenHandler, RemoveHandler,
+    ServerContext, Uri, Workspace, WorkspaceSource, WorkspaceSubset,
 };
 
 pub struct ChildrenExpander<W> {
@@ -25,7 +26,11 @@ where
     fn expand(workspace: &dyn Workspace, document: &Document) {
         if let Some(data) = document.data.as_latex() {
             let extras = &data.extras;
-            let mut all_targets = vec![&extras.implicit_links.aux, &extras.implicit_links.log];
+            let mut all_targets = vec![
+                &extras.implicit_links.aux,
+                &extras.implicit_links.bbl,
+                &extras.implicit_links.log,
+            ];
             for link in &extras.explicit_links {
                 if link
                     .as_component_name()
@@ -62,10 +67,22 @@ impl<W: Workspace> Workspace for ChildrenExpander<W> {
         self.workspace.open(uri, text, language, source)
     }
 
+    fn edit(&self, uri: Arc<Uri>, text: String, edit: TextRange) -> Option<Arc<Document>> {
+        self.workspace.edit(uri, text, edit)
+    }
+
+    fn context(&self) -> Arc<ServerContext> {
+        self.workspace.context()
+    }
+
     fn register_open_handler(&self, handler: OpenHandler) {
         self.workspace.register_open_handler(handler)
     }
 
+    fn register_remove_handler(&self, handler: RemoveHandler) {
+        self.workspace.register_remove_handler(handler)
+    }
+
     fn documents(&self) -> Vec<Arc<Document>> {
         self.workspace.documents()
     }
@@ -82,6 +99,10 @@ impl<W: Workspace> Workspace for ChildrenExpander<W> {
         self.workspace.close(uri)
     }
 
+    fn remove(&self, uri: &Uri) {
+        self.workspace.remove(uri)
+    }
+
     fn is_open(&self, uri: &Uri) -> bool {
         self.workspace.is_open(uri)
     }
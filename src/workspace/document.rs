@@ -1,5 +1,10 @@
-use std::{fmt, sync::Arc};
+use std::{
+    fmt, mem,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use cstree::{interning::Rodeo, TextRange, TextSize};
 use derive_more::From;
 
 use crate::{
@@ -14,6 +19,7 @@ use crate::{
 #[derive(Debug, Clone)]
 pub struct LatexDocumentData {
     pub root: latex::SyntaxNode,
+    pub interner: Arc<Rodeo>,
     pub extras: latex::Extras,
 }
 
@@ -63,12 +69,20 @@ impl DocumentData {
     }
 }
 
+/// The time spent on the last (re)parse of a document.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct DocumentTimings {
+    pub parse: Duration,
+    pub analysis: Duration,
+}
+
 #[derive(Clone)]
 pub struct Document {
     pub uri: Arc<Uri>,
     pub text: String,
     pub line_index: LineIndex,
     pub data: DocumentData,
+    pub timings: DocumentTimings,
 }
 
 impl fmt::Debug for Document {
@@ -84,33 +98,38 @@ impl Document {
         text: String,
         language: DocumentLanguage,
     ) -> Self {
-        let line_index = LineIndex::new(&text);
+        let line_index =
+            LineIndex::with_encoding(&text, *context.position_encoding.lock().unwrap());
+        let mut timings = DocumentTimings::default();
+        let start = Instant::now();
         let data = match language {
             DocumentLanguage::Latex => {
-                let root = latex::parse(&text).root;
-
-                let base_uri = match &context.options.read().unwrap().root_directory {
-                    Some(root_dir) => Uri::from_directory_path(root_dir)
-                        .map(Arc::new)
-                        .unwrap_or_else(|()| Arc::clone(&uri)),
-                    None => Arc::clone(&uri),
-                };
-                let mut context = LatexAnalyzerContext {
-                    inner: context,
-                    extras: latex::Extras::default(),
-                    document_uri: Arc::clone(&uri),
-                    base_uri,
-                };
+                let latex::Parse { root, interner } = latex::parse(&text);
+                timings.parse = start.elapsed();
+
+                let start = Instant::now();
+                let mut context = Self::latex_context(context, &uri);
                 latex::analyze(&mut context, &root);
                 let extras = context.extras;
-
-                LatexDocumentData { root, extras }.into()
+                timings.analysis = start.elapsed();
+
+                LatexDocumentData {
+                    root,
+                    interner,
+                    extras,
+                }
+                .into()
             }
             DocumentLanguage::Bibtex => {
                 let root = bibtex::parse(&text).root;
+                timings.parse = start.elapsed();
                 BibtexDocumentData { root }.into()
             }
-            DocumentLanguage::BuildLog => DocumentData::BuildLog(build_log::parse(&text)),
+            DocumentLanguage::BuildLog => {
+                let parse = build_log::parse(&text);
+                timings.parse = start.elapsed();
+                DocumentData::BuildLog(parse)
+            }
         };
 
         Self {
@@ -118,10 +137,113 @@ impl Document {
             text,
             line_index,
             data,
+            timings,
+        }
+    }
+
+    /// Creates a new version of `old_document` after the text inside `edit` (a range of the old text)
+    /// has been replaced. LaTeX documents are only reparsed and analyzed partially if possible.
+    pub fn reparse(
+        context: Arc<ServerContext>,
+        old_document: &Self,
+        text: String,
+        edit: TextRange,
+    ) -> Self {
+        let mut timings = DocumentTimings::default();
+        let data = old_document.data.as_latex().and_then(|old_data| {
+            let start = Instant::now();
+            let insert_end = usize::from(edit.end()) + text.len() - old_document.text.len();
+            let insert = text.get(usize::from(edit.start())..insert_end)?;
+            let reparse = latex::reparse(&old_data.root, &old_data.interner, edit, insert)?;
+            timings.parse = start.elapsed();
+
+            let start = Instant::now();
+            let mut context = Self::latex_context(Arc::clone(&context), &old_document.uri);
+            latex::analyze_incremental(
+                &mut context,
+                &old_data.extras,
+                &reparse.old_node,
+                &reparse.new_node,
+            )?;
+            timings.analysis = start.elapsed();
+
+            Some(LatexDocumentData {
+                root: reparse.parse.root,
+                interner: reparse.parse.interner,
+                extras: context.extras,
+            })
+        });
+
+        match data {
+            Some(data) => Self {
+                uri: Arc::clone(&old_document.uri),
+                line_index: LineIndex::with_encoding(&text, old_document.line_index.encoding()),
+                text,
+                data: data.into(),
+                timings,
+            },
+            None => Self::parse(
+                context,
+                Arc::clone(&old_document.uri),
+                text,
+                old_document.language(),
+            ),
+        }
+    }
+
+    fn latex_context(context: Arc<ServerContext>, uri: &Arc<Uri>) -> LatexAnalyzerContext {
+        let base_uri = match &context.options_for(uri).root_directory {
+            Some(root_dir) => Uri::from_directory_path(root_dir)
+                .map(Arc::new)
+                .unwrap_or_else(|()| Arc::clone(uri)),
+            None => Arc::clone(uri),
+        };
+
+        LatexAnalyzerContext {
+            inner: context,
+            extras: latex::Extras::default(),
+            document_uri: Arc::clone(uri),
+            base_uri,
         }
     }
 
     pub fn language(&self) -> DocumentLanguage {
         self.data.language()
     }
+
+    /// Returns a rough estimate of the heap memory used by the document
+    /// (text, line index and syntax tree).
+    ///
+    /// This is not a measurement: every node and token of the syntax tree is assumed to take
+    /// `SYNTAX_ELEMENT_SIZE` bytes, interned strings are not counted
+    /// and the analysis results (`extras`) are ignored. Use it to compare documents,
+    /// not as the actual memory usage of the server.
+    pub fn estimated_memory(&self) -> usize {
+        /// Approximate size of a node or token of the syntax tree in bytes.
+        const SYNTAX_ELEMENT_SIZE: usize = 32;
+
+        let line_index_size = self.line_index.newlines.len() * mem::size_of::<TextSize>()
+            + self
+                .line_index
+                .utf16_lines
+                .values()
+                .map(|chars| chars.len() * 2 * mem::size_of::<TextSize>())
+                .sum::<usize>();
+
+        let data_size = match &self.data {
+            DocumentData::Latex(data) => {
+                data.root.descendants_with_tokens().count() * SYNTAX_ELEMENT_SIZE
+            }
+            DocumentData::Bibtex(data) => {
+                data.root.descendants_with_tokens().count() * SYNTAX_ELEMENT_SIZE
+            }
+            DocumentData::BuildLog(parse) => parse
+                .errors
+                .iter()
+                .map(|error| mem::size_of_val(error) + error.message.len())
+                .sum(),
+        };
+
+        self.text.capacity() + line_index_size + data_size
+    }
 }
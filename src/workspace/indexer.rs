@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use log::warn;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use regex::RegexSet;
+
+use crate::{ServerContext, Uri, Workspace};
+
+const INDEXED_EXTENSIONS: &[&str] = &["tex", "bib", "sty", "cls", "aux", "bbl"];
+
+/// Finds the files inside of the given workspace folders that should be loaded in the background.
+/// The exclude patterns of a folder are matched against the paths relative to the folder,
+/// which use `/` as separator on all platforms.
+pub fn find_indexable_files(context: &ServerContext, folders: &[Arc<Uri>]) -> Vec<PathBuf> {
+    folders
+        .into_par_iter()
+        .filter_map(|folder| {
+            let path = folder.to_file_path().ok()?;
+            let exclude = match RegexSet::new(&context.options_for(folder).index.exclude) {
+                Ok(exclude) => exclude,
+                Err(why) => {
+                    warn!("Invalid index exclude pattern: {}", why);
+                    RegexSet::empty()
+                }
+            };
+
+            Some(find_files(&path, &path, &exclude))
+        })
+        .flatten()
+        .collect()
+}
+
+fn find_files(root: &Path, directory: &Path, exclude: &RegexSet) -> Vec<PathBuf> {
+    let entries: Vec<_> = fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .collect();
+
+    entries
+        .into_par_iter()
+        .flat_map(|entry| {
+            let path = entry.path();
+            if is_excluded(root, &path, exclude) {
+                return Vec::new();
+            }
+
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => find_files(root, &path, exclude),
+                Ok(file_type) if file_type.is_file() && is_indexable(&path) => vec![path],
+                _ => Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn is_excluded(root: &Path, path: &Path, exclude: &RegexSet) -> bool {
+    let relative_path = path.strip_prefix(root).unwrap_or(path);
+    let relative_path = relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    exclude.is_match(&relative_path)
+}
+
+fn is_indexable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            INDEXED_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        })
+}
+
+/// Loads the given files in parallel and calls `report` with the number of processed files.
+pub fn index_files(
+    workspace: &dyn Workspace,
+    files: Vec<PathBuf>,
+    report: impl Fn(usize) + Send + Sync,
+) {
+    let count = AtomicUsize::new(0);
+    files.into_par_iter().for_each(|path| {
+        if let Err(why) = workspace.load(path.clone()) {
+            warn!("Failed to index {}: {}", path.display(), why);
+        }
+
+        report(count.fetch_add(1, Ordering::SeqCst) + 1);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{create_workspace_fast, IndexOptions, Options};
+
+    use super::*;
+
+    fn create_context(root: &Path, exclude: Vec<String>) -> Arc<ServerContext> {
+        let context = ServerContext::new(root.to_path_buf());
+        let folder = Arc::new(Uri::from_directory_path(root).unwrap());
+        context
+            .workspace_folders
+            .write()
+            .unwrap()
+            .push(Arc::clone(&folder));
+        let options = Options {
+            index: IndexOptions { exclude },
+            ..Options::default()
+        };
+        context
+            .folder_options
+            .write()
+            .unwrap()
+            .insert(folder, options);
+        Arc::new(context)
+    }
+
+    fn folders(context: &ServerContext) -> Vec<Arc<Uri>> {
+        context.workspace_folders.read().unwrap().clone()
+    }
+
+    fn create_files(root: &Path) {
+        for name in &[
+            "main.tex",
+            "chapters/a.tex",
+            "refs.bib",
+            "build/main.aux",
+            "main.log",
+        ] {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_find_indexable_files() {
+        let directory = tempdir().unwrap();
+        create_files(directory.path());
+        let context = create_context(directory.path(), Vec::new());
+
+        let mut actual_files = find_indexable_files(&context, &folders(&context));
+        actual_files.sort();
+
+        let mut expected_files: Vec<_> =
+            ["main.tex", "chapters/a.tex", "refs.bib", "build/main.aux"]
+                .iter()
+                .map(|name| directory.path().join(name))
+                .collect();
+        expected_files.sort();
+
+        assert_eq!(actual_files, expected_files);
+    }
+
+    #[test]
+    fn test_find_indexable_files_exclude() {
+        let directory = tempdir().unwrap();
+        create_files(directory.path());
+        let context = create_context(directory.path(), vec!["build$".into(), r"\.bib$".into()]);
+
+        let mut actual_files = find_indexable_files(&context, &folders(&context));
+        actual_files.sort();
+
+        let mut expected_files: Vec<_> = ["main.tex", "chapters/a.tex"]
+            .iter()
+            .map(|name| directory.path().join(name))
+            .collect();
+        expected_files.sort();
+
+        assert_eq!(actual_files, expected_files);
+    }
+
+    #[test]
+    fn test_index_files() {
+        let directory = tempdir().unwrap();
+        create_files(directory.path());
+        let context = create_context(directory.path(), Vec::new());
+        let workspace = create_workspace_fast(Arc::clone(&context)).unwrap();
+
+        let files = find_indexable_files(&context, &folders(&context));
+        let file_count = files.len();
+        let max_count = AtomicUsize::new(0);
+        index_files(&workspace, files, |count| {
+            max_count.fetch_max(count, Ordering::SeqCst);
+        });
+
+        assert_eq!(max_count.into_inner(), file_count);
+        assert!(workspace
+            .get(&Uri::from_file_path(directory.path().join("refs.bib")).unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn test_find_indexable_files_exclude_relative() {
+        let directory = tempdir().unwrap();
+        create_files(directory.path());
+        let context = create_context(directory.path(), vec!["^chapters/".into()]);
+
+        let mut actual_files = find_indexable_files(&context, &folders(&context));
+        actual_files.sort();
+
+        let mut expected_files: Vec<_> = ["main.tex", "refs.bib", "build/main.aux"]
+            .iter()
+            .map(|name| directory.path().join(name))
+            .collect();
+        expected_files.sort();
+
+        assert_eq!(actual_files, expected_files);
+    }
+}
@@ -1,10 +1,12 @@
 use std::{fs, sync::Arc};
 
+use cstree::TextRange;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use rustc_hash::FxHashSet;
 
 use crate::{
-    Document, DocumentLanguage, OpenHandler, Uri, Workspace, WorkspaceSource, WorkspaceSubset,
+    Document, DocumentLanguage, OpenHandler, RemoveHandler, ServerContext, Uri, Workspace,
+    WorkspaceSource, WorkspaceSubset,
 };
 
 pub struct ParentExpander<W> {
@@ -69,10 +71,22 @@ where
         document
     }
 
+    fn edit(&self, uri: Arc<Uri>, text: String, edit: TextRange) -> Option<Arc<Document>> {
+        self.workspace.edit(uri, text, edit)
+    }
+
+    fn context(&self) -> Arc<ServerContext> {
+        self.workspace.context()
+    }
+
     fn register_open_handler(&self, handler: OpenHandler) {
         self.workspace.register_open_handler(handler)
     }
 
+    fn register_remove_handler(&self, handler: RemoveHandler) {
+        self.workspace.register_remove_handler(handler)
+    }
+
     fn documents(&self) -> Vec<Arc<Document>> {
         self.workspace.documents()
     }
@@ -89,6 +103,10 @@ where
         self.workspace.close(uri)
     }
 
+    fn remove(&self, uri: &Uri) {
+        self.workspace.remove(uri)
+    }
+
     fn is_open(&self, uri: &Uri) -> bool {
         self.workspace.is_open(uri)
     }
@@ -1,11 +1,12 @@
 use std::sync::{Arc, Mutex};
 
+use cstree::TextRange;
 use petgraph::{graphmap::UnGraphMap, visit::Dfs};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
-    Document, DocumentLanguage, OpenHandler, ServerContext, Uri, Workspace, WorkspaceSource,
-    WorkspaceSubset,
+    Document, DocumentLanguage, OpenHandler, RemoveHandler, ServerContext, Uri, Workspace,
+    WorkspaceSource, WorkspaceSubset,
 };
 
 #[derive(Clone)]
@@ -14,6 +15,7 @@ pub struct Storage {
     documents_by_uri: Arc<Mutex<FxHashMap<Arc<Uri>, Arc<Document>>>>,
     opened_documents: Arc<Mutex<FxHashSet<Arc<Uri>>>>,
     open_handlers: Arc<Mutex<Vec<OpenHandler>>>,
+    remove_handlers: Arc<Mutex<Vec<RemoveHandler>>>,
 }
 
 impl Storage {
@@ -23,8 +25,33 @@ impl Storage {
             documents_by_uri: Arc::default(),
             opened_documents: Arc::default(),
             open_handlers: Arc::default(),
+            remove_handlers: Arc::default(),
         }
     }
+
+    fn insert(&self, document: Arc<Document>, source: WorkspaceSource) -> Arc<Document> {
+        self.context.statistics.record_document(&document);
+        {
+            self.documents_by_uri
+                .lock()
+                .unwrap()
+                .insert(Arc::clone(&document.uri), Arc::clone(&document));
+        }
+
+        if source == WorkspaceSource::Client {
+            self.opened_documents
+                .lock()
+                .unwrap()
+                .insert(Arc::clone(&document.uri));
+        }
+
+        let handlers = { self.open_handlers.lock().unwrap().clone() };
+        for handler in handlers {
+            handler(Arc::new(self.clone()), Arc::clone(&document));
+        }
+
+        document
+    }
 }
 
 impl Workspace for Storage {
@@ -42,29 +69,32 @@ impl Workspace for Storage {
             text,
             language,
         ));
-        {
-            self.documents_by_uri
-                .lock()
-                .unwrap()
-                .insert(Arc::clone(&uri), Arc::clone(&document));
-        }
-
-        if source == WorkspaceSource::Client {
-            self.opened_documents.lock().unwrap().insert(uri);
-        }
+        self.insert(document, source)
+    }
 
-        let handlers = { self.open_handlers.lock().unwrap().clone() };
-        for handler in handlers {
-            handler(Arc::new(self.clone()), Arc::clone(&document));
-        }
+    fn edit(&self, uri: Arc<Uri>, text: String, edit: TextRange) -> Option<Arc<Document>> {
+        let old_document = self.get(&uri)?;
+        let document = Arc::new(Document::reparse(
+            Arc::clone(&self.context),
+            &old_document,
+            text,
+            edit,
+        ));
+        Some(self.insert(document, WorkspaceSource::Client))
+    }
 
-        document
+    fn context(&self) -> Arc<ServerContext> {
+        Arc::clone(&self.context)
     }
 
     fn register_open_handler(&self, handler: OpenHandler) {
         self.open_handlers.lock().unwrap().push(handler);
     }
 
+    fn register_remove_handler(&self, handler: RemoveHandler) {
+        self.remove_handlers.lock().unwrap().push(handler);
+    }
+
     fn documents(&self) -> Vec<Arc<Document>> {
         self.documents_by_uri
             .lock()
@@ -86,6 +116,23 @@ impl Workspace for Storage {
         self.opened_documents.lock().unwrap().remove(uri);
     }
 
+    fn remove(&self, uri: &Uri) {
+        if self.is_open(uri) {
+            return;
+        }
+
+        let uri = match self.documents_by_uri.lock().unwrap().remove_entry(uri) {
+            Some((uri, _)) => uri,
+            None => return,
+        };
+
+        log::debug!("Removing document: {}", uri);
+        let handlers = { self.remove_handlers.lock().unwrap().clone() };
+        for handler in handlers {
+            handler(Arc::new(self.clone()), Arc::clone(&uri));
+        }
+    }
+
     fn is_open(&self, uri: &Uri) -> bool {
         self.opened_documents.lock().unwrap().contains(uri)
     }
@@ -107,7 +154,11 @@ impl Workspace for Storage {
                 .and_then(|document| document.data.as_latex())
             {
                 let extras = &data.extras;
-                let mut all_targets = vec![&extras.implicit_links.aux, &extras.implicit_links.log];
+                let mut all_targets = vec![
+                    &extras.implicit_links.aux,
+                    &extras.implicit_links.bbl,
+                    &extras.implicit_links.log,
+                ];
                 for link in &extras.explicit_links {
                     all_targets.push(&link.targets);
                 }
@@ -134,3 +185,54 @@ impl Workspace for Storage {
         Some(WorkspaceSubset { documents })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn create_storage() -> Storage {
+        let storage = Storage::new(Arc::new(ServerContext::new(std::env::temp_dir())));
+        storage.open(
+            Arc::new(Uri::parse("file:///foo.tex").unwrap()),
+            String::new(),
+            DocumentLanguage::Latex,
+            WorkspaceSource::Server,
+        );
+        storage.open(
+            Arc::new(Uri::parse("file:///bar.tex").unwrap()),
+            String::new(),
+            DocumentLanguage::Latex,
+            WorkspaceSource::Client,
+        );
+        storage
+    }
+
+    #[test]
+    fn test_remove() {
+        let storage = create_storage();
+        let removed_count = Arc::new(AtomicUsize::new(0));
+        {
+            let removed_count = Arc::clone(&removed_count);
+            storage.register_remove_handler(Arc::new(move |_, _| {
+                removed_count.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+
+        let uri = Uri::parse("file:///foo.tex").unwrap();
+        storage.remove(&uri);
+        storage.remove(&uri);
+
+        assert!(!storage.has(&uri));
+        assert_eq!(removed_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_remove_open_document() {
+        let storage = create_storage();
+        let uri = Uri::parse("file:///bar.tex").unwrap();
+        storage.remove(&uri);
+        assert!(storage.has(&uri));
+    }
+}
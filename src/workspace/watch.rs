@@ -1,19 +1,27 @@
 use std::{
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc, Mutex},
 };
 
 use anyhow::Result;
+use cstree::TextRange;
 use log::warn;
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
 use rustc_hash::FxHashSet;
 
 use crate::{
-    Document, DocumentLanguage, OpenHandler, Uri, Workspace, WorkspaceSource, WorkspaceSubset,
+    Document, DocumentLanguage, OpenHandler, RemoveHandler, ServerContext, Uri, Workspace,
+    WorkspaceSource, WorkspaceSubset,
 };
 
+/// Watches the folders of the loaded documents
+/// unless the client notifies the server about changed files.
 pub struct DocumentWatcher<W> {
     workspace: Arc<W>,
+    context: Arc<ServerContext>,
     watcher: Mutex<RecommendedWatcher>,
     watched_paths: Mutex<FxHashSet<PathBuf>>,
 }
@@ -22,10 +30,11 @@ impl<W> DocumentWatcher<W>
 where
     W: Workspace + Send + Sync + 'static,
 {
-    pub fn new(workspace: Arc<W>) -> Result<Self> {
+    pub fn new(workspace: Arc<W>, context: Arc<ServerContext>) -> Result<Self> {
         let watcher = Self::create_watcher(Arc::clone(&workspace))?;
         Ok(Self {
             workspace,
+            context,
             watcher: Mutex::new(watcher),
             watched_paths: Mutex::default(),
         })
@@ -34,17 +43,72 @@ where
     fn create_watcher(workspace: Arc<W>) -> Result<RecommendedWatcher> {
         let watcher = Watcher::new_immediate(move |event: notify::Result<notify::Event>| {
             if let Ok(event) = event {
-                if event.kind.is_modify() {
-                    for path in event.paths {
-                        let _ = workspace.reload(path);
-                    }
-                }
+                handle_event(workspace.as_ref(), event);
             }
         })?;
         Ok(watcher)
     }
 }
 
+fn handle_event(workspace: &dyn Workspace, event: Event) {
+    match event.kind {
+        EventKind::Create(_) => {
+            for path in event.paths {
+                load_document(workspace, path);
+            }
+        }
+        EventKind::Remove(_) => {
+            for path in event.paths {
+                remove_document(workspace, &path);
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            for path in event.paths {
+                remove_document(workspace, &path);
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            for path in event.paths {
+                load_document(workspace, path);
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let mut paths = event.paths.into_iter();
+            if let (Some(old_path), Some(new_path)) = (paths.next(), paths.next()) {
+                remove_document(workspace, &old_path);
+                load_document(workspace, new_path);
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            for path in event.paths {
+                if path.exists() {
+                    load_document(workspace, path);
+                } else {
+                    remove_document(workspace, &path);
+                }
+            }
+        }
+        EventKind::Modify(_) => {
+            for path in event.paths {
+                let _ = workspace.reload(path);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn load_document(workspace: &dyn Workspace, path: PathBuf) {
+    if DocumentLanguage::by_path(&path).is_some() {
+        let _ = workspace.reload(path);
+    }
+}
+
+fn remove_document(workspace: &dyn Workspace, path: &Path) {
+    if let Ok(uri) = Uri::from_file_path(path) {
+        workspace.remove(&uri);
+    }
+}
+
 impl<W: Workspace> Workspace for DocumentWatcher<W> {
     fn open(
         &self,
@@ -54,7 +118,8 @@ impl<W: Workspace> Workspace for DocumentWatcher<W> {
         source: WorkspaceSource,
     ) -> Arc<Document> {
         let document = self.workspace.open(uri, text, language, source);
-        if document.uri.scheme() == "file" {
+        let client_watches_files = self.context.client_watches_files.load(Ordering::SeqCst);
+        if document.uri.scheme() == "file" && !client_watches_files {
             if let Ok(mut path) = document.uri.to_file_path() {
                 path.pop();
                 let mut watched_paths = self.watched_paths.lock().unwrap();
@@ -77,10 +142,22 @@ impl<W: Workspace> Workspace for DocumentWatcher<W> {
         document
     }
 
+    fn edit(&self, uri: Arc<Uri>, text: String, edit: TextRange) -> Option<Arc<Document>> {
+        self.workspace.edit(uri, text, edit)
+    }
+
+    fn context(&self) -> Arc<ServerContext> {
+        self.workspace.context()
+    }
+
     fn register_open_handler(&self, handler: OpenHandler) {
         self.workspace.register_open_handler(handler);
     }
 
+    fn register_remove_handler(&self, handler: RemoveHandler) {
+        self.workspace.register_remove_handler(handler);
+    }
+
     fn documents(&self) -> Vec<Arc<Document>> {
         self.workspace.documents()
     }
@@ -97,6 +174,10 @@ impl<W: Workspace> Workspace for DocumentWatcher<W> {
         self.workspace.close(uri)
     }
 
+    fn remove(&self, uri: &Uri) {
+        self.workspace.remove(uri)
+    }
+
     fn is_open(&self, uri: &Uri) -> bool {
         self.workspace.is_open(uri)
     }
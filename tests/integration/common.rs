@@ -280,6 +280,29 @@ impl ServerTester {
         Ok(symbols)
     }
 
+    pub fn pull_diagnostics(
+        &self,
+        uri: Url,
+        previous_result_id: Option<&str>,
+    ) -> Result<serde_json::Value> {
+        let request_id = RequestId::from(self.request_id.fetch_add(1, Ordering::SeqCst));
+
+        self.client.sender.send(
+            lsp_server::Request::new(
+                request_id.clone(),
+                "textDocument/diagnostic".to_string(),
+                serde_json::json!({
+                    "textDocument": TextDocumentIdentifier::new(uri),
+                    "previousResultId": previous_result_id,
+                }),
+            )
+            .into(),
+        )?;
+
+        let response = self.wait_for_response(request_id)?;
+        Ok(response.result.expect("document diagnostic request failed"))
+    }
+
     // pub fn change_configuration(&self, options: Options) -> Result<()> {
     //     self.client.sender.send(
     //         lsp_server::Notification::new(
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use lsp_types::ClientCapabilities;
+
+use crate::common::ServerTester;
+
+#[test]
+fn test_pull_document_diagnostics() -> Result<()> {
+    let server = ServerTester::launch_new_instance()?;
+    server.initialize(ClientCapabilities::default(), None)?;
+    let uri = server.open("main.tex", r#"\foo}"#, "latex", false)?;
+
+    let report = server.pull_diagnostics(uri.clone(), None)?;
+    assert_eq!(report["kind"], "full");
+    assert_eq!(report["items"].as_array().unwrap().len(), 1);
+
+    let result_id = report["resultId"].as_str().unwrap();
+    let report = server.pull_diagnostics(uri, Some(result_id))?;
+    assert_eq!(report["kind"], "unchanged");
+    assert_eq!(report["resultId"], result_id);
+    Ok(())
+}
+
+#[test]
+fn test_pull_document_diagnostics_changed() -> Result<()> {
+    let server = ServerTester::launch_new_instance()?;
+    server.initialize(ClientCapabilities::default(), None)?;
+    let uri = server.open("main.tex", r#"\foo"#, "latex", false)?;
+
+    let report = server.pull_diagnostics(uri, Some("foo"))?;
+    assert_eq!(report["kind"], "full");
+    assert!(report["items"].as_array().unwrap().is_empty());
+    Ok(())
+}
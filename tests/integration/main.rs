@@ -1,6 +1,7 @@
 mod common;
 #[cfg(feature = "completion")]
 mod completion;
+mod diagnostics;
 mod document_symbol;
 mod hover;
 mod issues;